@@ -0,0 +1,79 @@
+use crate::editor::{Editor, Direction};
+
+impl Editor {
+    fn pair_around_cursor(&self) -> Option<(char, char)> {
+        let line = &self.buffer[self.cursor.y];
+        let before = *line.get(self.cursor.x.checked_sub(1)?)?;
+        let after = *line.get(self.cursor.x)?;
+
+        self.syntax.autopairs.iter().find(|(open, close)| *open == before && *close == after).copied()
+    }
+
+    // quotes are not paired next to words or inside strings
+    fn can_pair(&self, open: char, close: char) -> bool {
+        let line = &self.buffer[self.cursor.y];
+        let word = |x: Option<usize>| x.and_then(|x| line.get(x)).map(|character| character.is_alphanumeric() || *character == '_').unwrap_or(false);
+
+        if word(Some(self.cursor.x)) {
+            return false;
+        }
+
+        open != close || !(word(self.cursor.x.checked_sub(1)) || self.syntax.in_string(self.cursor.y, line, self.cursor.x))
+    }
+
+    pub fn type_character(&mut self, character: char) {
+        if !self.options.get_bool("autopairs") {
+            self.insert(character);
+            return;
+        }
+
+        // typing the closing character in front of itself only moves over it
+        let closes = self.syntax.autopairs.iter().any(|(_, close)| *close == character);
+        if closes && self.buffer[self.cursor.y].get(self.cursor.x) == Some(&character) {
+            self.move_cursor(Direction::Right);
+            self.clamp = self.cursor.x;
+            self.refresh = true;
+            return;
+        }
+
+        let pair = self.syntax.autopairs.iter()
+            .find(|(open, close)| *open == character && self.can_pair(*open, *close))
+            .copied();
+
+        self.insert(character);
+
+        if let Some((_, close)) = pair {
+            self.buffer[self.cursor.y].insert(self.cursor.x, close);
+            self.changed(self.cursor.y, 1, 1);
+        }
+
+        self.clamp = self.cursor.x;
+    }
+
+    // backspace between an empty pair removes the closing half along with the opening one
+    pub fn remove_closing(&mut self) {
+        if self.options.get_bool("autopairs") && self.pair_around_cursor().is_some() {
+            self.buffer[self.cursor.y].remove(self.cursor.x);
+            self.changed(self.cursor.y, 1, 1);
+        }
+    }
+
+    // enter between brackets puts the closing one on its own line below an indented empty line
+    pub fn open_block(&mut self) -> bool {
+        if !self.options.get_bool("autopairs") || self.pair_around_cursor().map(|(open, close)| open == close).unwrap_or(true) {
+            return false;
+        }
+
+        let indentation = self.buffer[self.cursor.y].iter().take_while(|character| **character == ' ').count();
+        let inner = " ".repeat(indentation + self.shiftwidth());
+
+        self.folds.reveal(self.cursor.y);
+        self.insert_text(&format!("\n{}\n{}", inner, " ".repeat(indentation)));
+
+        self.cursor.y -= 1;
+        self.cursor.x = inner.len();
+        self.clamp = self.cursor.x;
+        self.scroll_to_cursor();
+        true
+    }
+}
@@ -0,0 +1,128 @@
+use crate::editor::{Editor, Cursor, Mode};
+use crate::editor::highlight::Syntax;
+
+// how many lines are searched in each direction before giving up
+const MAX_BRACKET_LINES: usize = 500;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Bracket {
+    x: usize,
+    y: usize,
+    length: usize,
+    pair: usize,
+    open: bool,
+}
+
+impl Editor {
+    fn brackets(&self, y: usize) -> Vec<Bracket> {
+        Syntax::find_pairs_in(&self.highlighted(y), &self.buffer[y], &self.syntax.pairs)
+            .into_iter()
+            .map(|(x, length, pair, open)| Bracket { x, y, length, pair, open })
+            .collect()
+    }
+
+    // the brackets after `from` going forward or before it going backward, line by line
+    fn scan(&self, from: &Bracket, forward: bool) -> impl Iterator<Item = Bracket> + '_ {
+        let from = *from;
+        let lines: Box<dyn Iterator<Item = usize>> = if forward {
+            Box::new(from.y..(from.y + MAX_BRACKET_LINES).min(self.buffer.len()))
+        } else {
+            Box::new((from.y.saturating_sub(MAX_BRACKET_LINES)..=from.y).rev())
+        };
+
+        lines.flat_map(move |y| {
+            let mut brackets = self.brackets(y);
+            if !forward {
+                brackets.reverse();
+            }
+            brackets
+        }).filter(move |bracket| {
+            if bracket.y != from.y {
+                true
+            } else if forward {
+                bracket.x > from.x
+            } else {
+                bracket.x < from.x
+            }
+        })
+    }
+
+    fn matching(&self, bracket: &Bracket) -> Option<Bracket> {
+        let mut depth = 0;
+
+        for other in self.scan(bracket, bracket.open).filter(|other| other.pair == bracket.pair) {
+            if other.open == bracket.open {
+                depth += 1;
+            } else if depth == 0 {
+                return Some(other);
+            } else {
+                depth -= 1;
+            }
+        }
+
+        None
+    }
+
+    fn bracket_under_cursor(&self) -> Option<Bracket> {
+        let brackets = self.brackets(self.cursor.y);
+        let under = |x: usize| brackets.iter().find(|bracket| bracket.x <= x && x < bracket.x + bracket.length).copied();
+
+        // in insert mode the bracket that was just typed counts as well
+        under(self.cursor.x).or_else(|| if self.mode == Mode::Insert && self.cursor.x > 0 { under(self.cursor.x - 1) } else { None })
+    }
+
+    // the closest opening bracket before the cursor that is not closed before it
+    fn enclosing_bracket(&self) -> Option<Bracket> {
+        let cursor = Bracket {
+            x: self.cursor.x,
+            y: self.cursor.y,
+            length: 0,
+            pair: 0,
+            open: false,
+        };
+        let mut depth = vec![0; self.syntax.pairs.len()];
+
+        for bracket in self.scan(&cursor, false) {
+            if !bracket.open {
+                depth[bracket.pair] += 1;
+            } else if depth[bracket.pair] == 0 {
+                return Some(bracket);
+            } else {
+                depth[bracket.pair] -= 1;
+            }
+        }
+
+        None
+    }
+
+    // the pair under the cursor or the one around it as (start, length) of both halves
+    pub fn bracket_pair(&self) -> Option<[(Cursor, usize); 2]> {
+        if self.buffer.is_empty() || self.cursor.y >= self.buffer.len() {
+            return None;
+        }
+
+        let bracket = self.bracket_under_cursor().or_else(|| self.enclosing_bracket())?;
+        let matching = self.matching(&bracket)?;
+
+        Some([bracket, matching].map(|bracket| (Cursor { x: bracket.x, y: bracket.y }, bracket.length)))
+    }
+
+    // like vim the bracket under the cursor is used, otherwise the next one on the line
+    pub fn jump_bracket(&mut self) {
+        let bracket = self.bracket_under_cursor()
+            .or_else(|| self.brackets(self.cursor.y).into_iter().find(|bracket| bracket.x > self.cursor.x));
+
+        match bracket.and_then(|bracket| self.matching(&bracket)) {
+            Some(matching) => {
+                self.cursor.x = matching.x;
+                self.cursor.y = matching.y;
+                self.clamp = self.cursor.x;
+
+                self.folds.reveal(self.cursor.y);
+                self.scroll_to_cursor();
+                self.refresh = true;
+            },
+            None => self.log("No matching bracket"),
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use crate::editor::{Editor, Cursor};
+use crate::editor::buffermanager::BufferManager;
+use crate::editor::input::{Key, KeyEvent, Modifiers};
+use crate::editor::list::List;
+use crate::editor::ex;
+
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const PARENT: &str = "../";
+const SORTS: [&str; 3] = ["name", "time", "size"];
+
+// a change to the files that waits for `y`
+#[derive(Debug)]
+pub enum Operation {
+    Create { path: PathBuf, directory: bool },
+    Move { from: PathBuf, to: PathBuf },
+    Copy { from: PathBuf, to: PathBuf },
+    Delete(PathBuf),
+}
+
+impl Operation {
+    fn question(&self) -> String {
+        match self {
+            Operation::Create { path, .. } => format!("Create `{}`? (y/n)", path.display()),
+            Operation::Move { from, to } => format!("Move `{}` to `{}`? (y/n)", from.display(), to.display()),
+            Operation::Copy { from, to } => format!("Copy `{}` to `{}`? (y/n)", from.display(), to.display()),
+            Operation::Delete(path) => format!("Delete `{}`? (y/n)", path.display()),
+        }
+    }
+
+    fn done(&self) -> String {
+        match self {
+            Operation::Create { path, .. } => format!("created `{}`", path.display()),
+            Operation::Move { from, to } => format!("moved `{}` to `{}`", from.display(), to.display()),
+            Operation::Copy { from, to } => format!("copied `{}` to `{}`", from.display(), to.display()),
+            Operation::Delete(path) => format!("deleted `{}`", path.display()),
+        }
+    }
+
+    // the path whose entry is selected afterwards
+    fn target(&self) -> Option<&Path> {
+        match self {
+            Operation::Create { path, .. } => Some(path),
+            Operation::Move { to, .. } | Operation::Copy { to, .. } => Some(to),
+            Operation::Delete(_) => None,
+        }
+    }
+
+    fn run(&self) -> Result<(), Box<dyn std::error::Error>> {
+        match self {
+            Operation::Create { path, directory: true } => fs::create_dir_all(path)?,
+            Operation::Create { path, directory: false } => {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::OpenOptions::new().write(true).create_new(true).open(path)?;
+            },
+            Operation::Move { from, to } => fs::rename(from, to)?,
+            Operation::Copy { from, to } => copy_recursive(from, to)?,
+            Operation::Delete(path) if fs::symlink_metadata(path)?.is_dir() => fs::remove_dir_all(path)?,
+            Operation::Delete(path) => fs::remove_file(path)?,
+        }
+
+        Ok(())
+    }
+}
+
+struct Entry {
+    name: String,
+    directory: bool,
+    modified: SystemTime,
+    size: u64,
+}
+
+// paths are shown from the working directory when they are inside it
+fn relative(path: &Path) -> String {
+    let relative = env::current_dir().ok()
+        .and_then(|cwd| fs::canonicalize(cwd).ok())
+        .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf));
+
+    match relative {
+        Some(relative) => relative.display().to_string(),
+        None => path.display().to_string(),
+    }
+}
+
+// the name of a listing ends in a slash, the working directory is `./`
+fn listing_name(path: &Path) -> String {
+    match relative(path) {
+        name if name.is_empty() => String::from("./"),
+        name if name.ends_with('/') => name,
+        name => format!("{}/", name),
+    }
+}
+
+fn entries(path: &Path, hidden: bool, sort: &str) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+    let mut entries = Vec::new();
+
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if !hidden && name.starts_with('.') {
+            continue;
+        }
+
+        // links are listed as what they point to
+        let metadata = match fs::metadata(entry.path()) {
+            Ok(metadata) => metadata,
+            Err(_) => entry.metadata()?,
+        };
+
+        entries.push(Entry {
+            name,
+            directory: metadata.is_dir(),
+            modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            size: metadata.len(),
+        });
+    }
+
+    // directories come first, the newest and the largest entries are at the top
+    entries.sort_by(|a, b| {
+        let order = match sort {
+            "time" => b.modified.cmp(&a.modified),
+            "size" => b.size.cmp(&a.size),
+            _ => std::cmp::Ordering::Equal,
+        };
+
+        b.directory.cmp(&a.directory).then(order).then_with(|| a.name.cmp(&b.name))
+    });
+
+    Ok(entries)
+}
+
+// links are copied as links, not as what they point to
+fn copy_recursive(from: &Path, to: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(from)?;
+
+    if metadata.is_symlink() {
+        copy_link(from, to)?;
+    } else if metadata.is_dir() {
+        fs::create_dir(to)?;
+
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else {
+        fs::copy(from, to)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_link(from: &Path, to: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(from)?, to)
+}
+
+#[cfg(windows)]
+fn copy_link(from: &Path, to: &Path) -> io::Result<()> {
+    let target = fs::read_link(from)?;
+
+    if fs::metadata(from).map(|metadata| metadata.is_dir()).unwrap_or(false) {
+        std::os::windows::fs::symlink_dir(target, to)
+    } else {
+        std::os::windows::fs::symlink_file(target, to)
+    }
+}
+
+// a directory copied or moved into itself would never stop growing
+fn is_inside(from: &Path, to: &Path) -> io::Result<bool> {
+    if !fs::symlink_metadata(from)?.is_dir() {
+        return Ok(false);
+    }
+
+    let from = fs::canonicalize(from)?;
+    let existing = to.ancestors().find_map(|ancestor| fs::canonicalize(ancestor).ok());
+
+    Ok(existing.map(|existing| existing.starts_with(from)).unwrap_or(false))
+}
+
+impl Editor {
+    // shows the entries of a directory in the current buffer, it is read again when the buffer is
+    pub fn open_directory(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let path = fs::canonicalize(path)?;
+        let name = listing_name(&path);
+
+        let entries = entries(&path, self.options.get_bool("dirhidden"), &self.options.get_str("dirsort"))?;
+
+        let mut lines = Vec::new();
+        let mut locations = Vec::new();
+
+        if let Some(parent) = path.parent() {
+            lines.push(String::from(PARENT));
+            locations.push((listing_name(parent), Cursor::new()));
+        }
+
+        for entry in entries {
+            let target = path.join(&entry.name);
+
+            if entry.directory {
+                lines.push(format!("{}/", entry.name));
+                locations.push((listing_name(&target), Cursor::new()));
+            } else {
+                lines.push(entry.name);
+                locations.push((relative(&target), Cursor::new()));
+            }
+        }
+
+        self.show_list(&name, List::directory(&path.to_string_lossy(), lines, locations))?;
+
+        // entries may be gone since the listing was last read
+        self.cursor.y = self.cursor.y.min(self.buffer.len() - 1);
+        Ok(())
+    }
+
+    fn directory(&self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        self.list.as_ref()
+            .and_then(|list| list.path())
+            .map(PathBuf::from)
+            .ok_or_else(|| "Not a directory listing".into())
+    }
+
+    // the entry of the cursor line, the parent can not be changed from its child
+    fn entry(&self) -> Result<(PathBuf, String), Box<dyn std::error::Error>> {
+        let directory = self.directory()?;
+        let line = self.buffer[self.cursor.y].iter().collect::<String>();
+
+        if line.is_empty() {
+            return Err("The directory is empty".into());
+        }
+
+        if line == PARENT {
+            return Err(format!("`{}` can not be changed from here", PARENT).into());
+        }
+
+        let name = line.trim_end_matches('/').to_string();
+        Ok((directory.join(&name), name))
+    }
+
+    // places the cursor on the line of the entry, when it is listed
+    fn select_entry(&mut self, name: &str) {
+        let directory = format!("{}/", name);
+
+        if let Some(y) = self.buffer.iter().position(|line| {
+            let line = line.iter().collect::<String>();
+            line == name || line == directory
+        }) {
+            self.goto(Cursor { x: 0, y });
+        }
+    }
+
+    fn enter_directory(&mut self, path: &str, select: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        self.open_directory(path)?;
+        self.reset();
+        self.matches.matches = Vec::new();
+
+        match select {
+            Some(name) => self.select_entry(name),
+            None => self.goto(Cursor::new()),
+        }
+
+        Ok(())
+    }
+
+    fn refresh_directory(&mut self, select: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        let directory = self.directory()?;
+        self.open_directory(&directory.to_string_lossy())?;
+
+        if let Some(name) = select {
+            self.select_entry(name);
+        }
+
+        Ok(())
+    }
+
+    // directories are opened in place of the listing, files in their own buffer
+    fn open_entry(&mut self, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        let location = self.list.as_ref().and_then(|list| list.location(self.cursor.y).cloned());
+
+        let (filename, cursor) = match location {
+            Some(location) => location,
+            None => return Ok(()),
+        };
+
+        if self.buffer[self.cursor.y].iter().collect::<String>() == PARENT {
+            return self.parent_directory();
+        }
+
+        if Path::new(&filename).is_dir() {
+            return self.enter_directory(&filename, None);
+        }
+
+        manager.open(self, &filename)?;
+        self.goto(cursor);
+        Ok(())
+    }
+
+    // going up keeps the cursor on the directory that was left
+    fn parent_directory(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let current = self.directory()?;
+        let parent = current.parent().ok_or("Already at the root")?;
+        let name = current.file_name().map(|name| name.to_string_lossy().to_string());
+
+        self.enter_directory(&parent.to_string_lossy(), name.as_deref())
+    }
+
+    // the command is typed out for the entry so it can be edited before it runs
+    fn prompt(&mut self, command: &str) {
+        for character in command.chars() {
+            self.typeahead.push_back((KeyEvent::new(Key::Char(character)), false));
+        }
+    }
+
+    // the keys of a directory listing, the others move the cursor and search like in any buffer
+    pub fn browser_key(&mut self, event: &KeyEvent, manager: &mut BufferManager) -> bool {
+        if self.list.as_ref().and_then(|list| list.path()).is_none() || event.modifiers != Modifiers::NONE {
+            return false;
+        }
+
+        let result = match event.key {
+            Key::Enter => self.open_entry(manager),
+            Key::Char('-') => self.parent_directory(),
+            Key::Char('a') => {
+                self.prompt(":create ");
+                Ok(())
+            },
+            Key::Char('r') | Key::Char('c') => self.entry().map(|(_, name)| {
+                let command = if event.key == Key::Char('r') { "move" } else { "copy" };
+                self.prompt(&format!(":{} {}", command, ex::escape(&name)));
+            }),
+            Key::Char('D') => self.delete_entry(),
+            Key::Char('s') => {
+                let sort = self.options.get_str("dirsort");
+                let next = SORTS[(SORTS.iter().position(|known| *known == sort).unwrap_or(0) + 1) % SORTS.len()];
+
+                self.options.set(&format!("dirsort={}", next), false)
+                    .and_then(|_| self.refresh_directory(None))
+                    .map(|_| self.log(&format!("sorted by {}", next)))
+            },
+            Key::Char('.') => {
+                let hidden = !self.options.get_bool("dirhidden");
+
+                self.options.set(if hidden { "dirhidden" } else { "nodirhidden" }, false)
+                    .and_then(|_| self.refresh_directory(None))
+                    .map(|_| self.log(if hidden { "showing hidden files" } else { "hiding hidden files" }))
+            },
+            _ => return false,
+        };
+
+        if let Err(err) = result {
+            self.log(&err.to_string());
+        }
+
+        self.refresh = true;
+        true
+    }
+
+    // the answer to a question asked in the status line, anything but `y` cancels
+    pub fn confirm_key(&mut self, event: &KeyEvent) -> bool {
+        let operation = match self.confirm.take() {
+            Some(operation) => operation,
+            None => return false,
+        };
+
+        if event.key != Key::Char('y') || event.modifiers != Modifiers::NONE {
+            self.log("Cancelled");
+            return true;
+        }
+
+        // the first part of the new name is the entry listed in the directory
+        let directory = self.directory().ok();
+        let select = operation.target()
+            .zip(directory.as_deref())
+            .and_then(|(target, directory)| target.strip_prefix(directory).ok())
+            .and_then(|name| name.iter().next())
+            .map(|name| name.to_string_lossy().to_string());
+
+        match operation.run().and_then(|_| self.refresh_directory(select.as_deref())) {
+            Ok(()) => self.log(&operation.done()),
+            Err(err) => self.log(&err.to_string()),
+        }
+
+        true
+    }
+
+    fn ask(&mut self, operation: Operation) {
+        self.log(&operation.question());
+        self.confirm = Some(operation);
+    }
+
+    // :create makes a directory when the name ends in a slash
+    pub fn create_entry(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let directory = self.directory()?;
+
+        if name.is_empty() {
+            return Err("Usage: :create NAME".into());
+        }
+
+        self.ask(Operation::Create {
+            path: directory.join(name),
+            directory: name.ends_with('/'),
+        });
+        Ok(())
+    }
+
+    // :move and :copy, the entry of the cursor line gets the new name
+    pub fn transfer_entry(&mut self, name: &str, copy: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let (from, _) = self.entry()?;
+
+        if name.is_empty() {
+            return Err(format!("Usage: :{} NAME", if copy { "copy" } else { "move" }).into());
+        }
+
+        let to = self.directory()?.join(name.trim_end_matches('/'));
+
+        if fs::symlink_metadata(&to).is_ok() {
+            return Err(format!("`{}` already exists", to.display()).into());
+        }
+
+        if is_inside(&from, &to)? {
+            return Err(format!("`{}` can not be put inside itself", from.display()).into());
+        }
+
+        self.ask(if copy { Operation::Copy { from, to } } else { Operation::Move { from, to } });
+        Ok(())
+    }
+
+    pub fn delete_entry(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let (path, _) = self.entry()?;
+
+        self.ask(Operation::Delete(path));
+        Ok(())
+    }
+}
@@ -1,15 +1,35 @@
 use crate::editor::*;
+use crate::editor::options::Value;
+use crate::editor::list::List;
 
+use std::collections::HashMap;
+use std::fs;
+
+pub const NOT_WRITTEN: &str = "No write since last change (add ! to override)";
 
 #[derive(Debug)]
 pub struct Buffer {
     matches: Matches,
-    filename:  String,
+    pub filename:  String,
 
     clamp: usize,
     cursor: Cursor,
     screen: Screen,
     syntax: highlight::Syntax,
+    folds: Folds,
+    options: HashMap<String, Value>,
+    list: Option<List>,
+    marks: HashMap<char, usize>,
+    // the text while another buffer is shown, without it the file is read again
+    lines: Option<Vec<Vec<char>>>,
+    modified: bool,
+}
+
+impl Buffer {
+    // the edited text of a buffer that is not shown
+    pub fn kept(&self) -> Option<&Vec<Vec<char>>> {
+        self.lines.as_ref()
+    }
 }
 
 #[derive(Debug)]
@@ -39,10 +59,21 @@ impl BufferManager {
             cursor: editor.cursor,
             screen: editor.screen,
             syntax: editor.syntax.clone(),
+            folds: editor.folds.clone(),
+            options: editor.options.buffer.clone(),
+            list: editor.list.clone(),
+            marks: editor.marks.clone(),
+            lines: None,
+            modified: editor.modified,
         });
     }
 
-    pub fn close_buffer(&mut self, editor: &mut Editor) -> Result<(), Box<dyn std::error::Error>> {
+    // the edits of the buffer are lost unless `force` is set
+    pub fn close_buffer(&mut self, editor: &mut Editor, force: bool) -> Result<(), Box<dyn std::error::Error>> {
+        if editor.modified && !force {
+            return Err(NOT_WRITTEN.into());
+        }
+
         if self.buffers.len() > 1 {
             let _ = self.buffers.remove(self.current);
             self.previous_buffer(editor)?;
@@ -59,11 +90,24 @@ impl BufferManager {
             cursor: editor.cursor,
             screen: editor.screen,
             syntax: editor.syntax.clone(),
+            folds: editor.folds.clone(),
+            options: editor.options.buffer.clone(),
+            list: editor.list.clone(),
+            marks: editor.marks.clone(),
+            lines: if editor.list.is_none() { Some(editor.buffer.clone()) } else { None },
+            modified: editor.modified,
         };
     }
 
     pub fn reload(&mut self, editor: &mut Editor) -> Result<(), Box<dyn std::error::Error>> {
-        editor.open_file(&self.buffers[self.current].filename)?;
+        match self.buffers[self.current].list.clone() {
+            Some(list) if list.path().is_none() => editor.show_list(&self.buffers[self.current].filename, list)?,
+            Some(_) => editor.open_file(&self.buffers[self.current].filename)?,
+            None => match self.buffers[self.current].lines.clone() {
+                Some(lines) => editor.show_file(&self.buffers[self.current].filename, lines)?,
+                None => editor.open_file(&self.buffers[self.current].filename)?,
+            },
+        }
 
         editor.cursor = self.buffers[self.current].cursor;
         editor.screen = self.buffers[self.current].screen;
@@ -72,12 +116,122 @@ impl BufferManager {
 
         editor.clamp = self.buffers[self.current].clamp;
         editor.syntax = self.buffers[self.current].syntax.clone();
+        editor.folds = self.buffers[self.current].folds.clone();
+        editor.options.buffer = self.buffers[self.current].options.clone();
+        editor.marks = self.buffers[self.current].marks.clone();
+        editor.modified = self.buffers[self.current].modified;
+        editor.mark_signs();
 
         editor.refresh = true;
 
         Ok(())
     }
 
+    // the file of the current buffer is read again, its edits are dropped
+    pub fn revert(&mut self, editor: &mut Editor) -> Result<(), Box<dyn std::error::Error>> {
+        self.save_buffer(editor);
+        self.buffers[self.current].lines = None;
+        self.buffers[self.current].modified = false;
+        self.reload(editor)
+    }
+
+    // quitting loses the edits of every buffer that was not written
+    pub fn check_written(&self, editor: &Editor) -> Result<(), Box<dyn std::error::Error>> {
+        if editor.modified {
+            return Err(NOT_WRITTEN.into());
+        }
+
+        match self.buffers.iter().enumerate().find(|(index, buffer)| *index != self.current && buffer.modified) {
+            Some((_, buffer)) => Err(format!("No write since last change of `{}` (add ! to override)", buffer.filename).into()),
+            None => Ok(()),
+        }
+    }
+
+    // switches to the buffer of the file or opens it in a new one
+    pub fn open(&mut self, editor: &mut Editor, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let path = fs::canonicalize(filename)?;
+        let found = self.buffers.iter().position(|buffer| fs::canonicalize(&buffer.filename).map(|known| known == path).unwrap_or(false));
+
+        match found {
+            Some(index) if index == self.current => {},
+            Some(index) => {
+                self.save_buffer(editor);
+                self.current = index;
+                self.reload(editor)?;
+            },
+            None => self.add(editor, filename)?,
+        }
+
+        Ok(())
+    }
+
+    // opens the file in a new buffer after the others, even when it is already open
+    pub fn add(&mut self, editor: &mut Editor, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.save_buffer(editor);
+
+        editor.open_file(filename)?;
+        editor.options.buffer.clear();
+        editor.marks.clear();
+        editor.reset();
+
+        self.load_buffer(editor);
+        self.current = self.buffers.len() - 1;
+        self.reload(editor)
+    }
+
+    // shows the list in the buffer of the same name, which is opened when there is none
+    pub fn open_list(&mut self, editor: &mut Editor, name: &str, list: List) -> Result<(), Box<dyn std::error::Error>> {
+        self.save_buffer(editor);
+
+        match self.buffers.iter().position(|buffer| buffer.list.is_some() && buffer.filename == name) {
+            Some(index) => self.current = index,
+            None => {
+                editor.show_list(name, list.clone())?;
+                editor.options.buffer.clear();
+                editor.marks.clear();
+
+                self.load_buffer(editor);
+                self.current = self.buffers.len() - 1;
+            },
+        }
+
+        let buffer = &mut self.buffers[self.current];
+        buffer.list = Some(list);
+        buffer.cursor = Cursor::new();
+        buffer.clamp = 0;
+        buffer.screen.reset();
+
+        self.reload(editor)
+    }
+
+    // :b takes the number of a buffer or a part of its name that only one buffer has
+    pub fn select(&mut self, editor: &mut Editor, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let exact = self.buffers.iter().position(|buffer| buffer.filename == name);
+
+        let found = match (name.parse::<usize>(), exact) {
+            (Ok(number), _) => (1..=self.buffers.len()).filter(|known| *known == number).map(|number| number - 1).collect(),
+            (_, Some(index)) => vec![index],
+            _ => self.buffers.iter().enumerate()
+                .filter(|(_, buffer)| buffer.filename.contains(name))
+                .map(|(index, _)| index)
+                .collect::<Vec<usize>>(),
+        };
+
+        let index = match found.as_slice() {
+            [] => return Err(format!("No matching buffer for `{}`", name).into()),
+            [index] => *index,
+            _ => return Err(format!("More than one match for `{}`", name).into()),
+        };
+
+        if index != self.current {
+            self.save_buffer(editor);
+            self.current = index;
+            self.reload(editor)?;
+        }
+
+        Ok(())
+    }
+
     pub fn next_buffer(&mut self, editor: &mut Editor) -> Result<(), Box<dyn std::error::Error>> {
         if self.current < self.buffers.len() - 1 {
             self.current += 1;
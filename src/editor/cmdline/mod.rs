@@ -0,0 +1,343 @@
+use crate::editor::{Editor, Mode, config_path, write_config};
+use crate::editor::buffermanager::BufferManager;
+use crate::editor::ex::{self, Complete};
+use crate::editor::input::{Key, KeyEvent};
+use crate::editor::render::Frame;
+
+use std::fs;
+
+const MAX_HISTORY: usize = 200;
+
+struct Wildmenu {
+    candidates: Vec<String>,
+    selected: Option<usize>,
+    // the completed word starts here in the command line
+    start: usize,
+}
+
+pub struct CommandLine {
+    text: Vec<char>,
+    cursor: usize,
+    history: Vec<String>,
+    // the history entry that is shown and the text it has to start with
+    recall: Option<(usize, String)>,
+    wildmenu: Option<Wildmenu>,
+}
+
+fn common_prefix(candidates: &[String]) -> String {
+    let mut prefix = candidates[0].chars().collect::<Vec<char>>();
+
+    for candidate in &candidates[1..] {
+        let length = prefix.iter().zip(candidate.chars()).take_while(|(a, b)| **a == *b).count();
+        prefix.truncate(length);
+    }
+
+    prefix.into_iter().collect()
+}
+
+// the entries of the directory part of the word that start with the rest of it, hidden ones only
+// when the rest starts with a dot
+fn files(word: &str) -> Vec<String> {
+    let (directory, prefix) = match word.rfind('/') {
+        Some(index) => word.split_at(index + 1),
+        None => ("", word),
+    };
+
+    let mut names = fs::read_dir(if directory.is_empty() { "." } else { directory }).into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+
+            let slash = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", directory, name, slash))
+        })
+        .collect::<Vec<String>>();
+
+    names.sort();
+    names
+}
+
+impl CommandLine {
+    pub fn new() -> CommandLine {
+        let history = config_path("history").ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .unwrap_or_default()
+            .lines()
+            .map(String::from)
+            .collect();
+
+        CommandLine {
+            text: Vec::new(),
+            cursor: 0,
+            history,
+            recall: None,
+            wildmenu: None,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.text.iter().collect()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn insert(&mut self, text: &str) {
+        for character in text.chars() {
+            self.text.insert(self.cursor, character);
+            self.cursor += 1;
+        }
+
+        self.recall = None;
+    }
+
+    fn set(&mut self, text: &str) {
+        self.text = text.chars().collect();
+        self.cursor = self.text.len();
+    }
+
+    // the last command goes to the end, an older copy of it is dropped
+    fn remember(&mut self, command: &str) {
+        self.history.retain(|known| known != command);
+        self.history.push(command.to_string());
+
+        if self.history.len() > MAX_HISTORY {
+            self.history.drain(..self.history.len() - MAX_HISTORY);
+        }
+
+        let _ = write_config("history", &(self.history.join("\n") + "\n"));
+    }
+
+    // only the entries that start with what was typed before the first Up are shown
+    fn recall(&mut self, older: bool) {
+        let (index, prefix) = self.recall.clone().unwrap_or((self.history.len(), self.text()));
+        let current = self.text();
+
+        let found = if older {
+            self.history[..index].iter().enumerate().rev()
+                .find(|(_, entry)| entry.starts_with(&prefix) && **entry != current)
+        } else {
+            self.history.iter().enumerate().skip(index + 1)
+                .find(|(_, entry)| entry.starts_with(&prefix) && **entry != current)
+        };
+
+        match found {
+            Some((index, entry)) => {
+                let entry = entry.clone();
+                self.set(&entry);
+                self.recall = Some((index, prefix));
+            },
+            // going past the newest entry brings back what was typed
+            None if !older && self.recall.is_some() => {
+                self.set(&prefix);
+                self.recall = None;
+            },
+            None => {},
+        }
+    }
+}
+
+impl Editor {
+    pub fn open_command_line(&mut self) {
+        self.cmdline.set(":");
+        self.cmdline.recall = None;
+        self.cmdline.wildmenu = None;
+        self.mode = Mode::Command;
+    }
+
+    fn close_command_line(&mut self) {
+        self.cmdline.set("");
+        self.cmdline.recall = None;
+        self.cmdline.wildmenu = None;
+        self.mode = Mode::Normal;
+        self.log("");
+    }
+
+    // the candidates for the word before the cursor and where the word starts
+    fn command_candidates(&self, manager: &BufferManager) -> (usize, Vec<String>) {
+        let before = self.cmdline.text[..self.cmdline.cursor].iter().collect::<String>();
+
+        let (name, arguments) = match before.split_once(' ') {
+            Some(split) => split,
+            None => {
+                let typed = before.strip_prefix(':').unwrap_or(&before);
+                let mut commands = ex::names().into_iter()
+                    .filter(|command| command.starts_with(typed))
+                    .map(|command| format!(":{}", command))
+                    .collect::<Vec<String>>();
+
+                commands.sort();
+                return (0, commands);
+            },
+        };
+
+        // the word is found and matched without its escapes, the candidates are put back escaped
+        let (start, index, word) = ex::last_argument(arguments);
+        let start = self.cmdline.cursor - (arguments.chars().count() - start);
+
+        let candidates = match ex::completion(name, index) {
+            Complete::File => files(&word),
+            Complete::Option => {
+                let mut names = self.options.names().into_iter()
+                    .filter(|option| option.starts_with(&word))
+                    .map(String::from)
+                    .collect::<Vec<String>>();
+
+                names.sort();
+                names
+            },
+            Complete::Buffer => manager.buffers.iter()
+                .map(|buffer| buffer.filename.clone())
+                .filter(|filename| filename.contains(&word))
+                .collect(),
+            Complete::Nothing => Vec::new(),
+        };
+
+        (start, candidates.iter().map(|candidate| ex::escape(candidate)).collect())
+    }
+
+    // the first tab completes as far as the candidates agree, the next ones go through them
+    fn complete_command_line(&mut self, forward: bool, manager: &BufferManager) {
+        if let Some(wildmenu) = &mut self.cmdline.wildmenu {
+            let count = wildmenu.candidates.len();
+
+            wildmenu.selected = Some(match (wildmenu.selected, forward) {
+                (None, true) => 0,
+                (None, false) => count - 1,
+                (Some(selected), true) => (selected + 1) % count,
+                (Some(selected), false) => (selected + count - 1) % count,
+            });
+
+            let (start, candidate) = (wildmenu.start, wildmenu.candidates[wildmenu.selected.unwrap_or(0)].clone());
+            self.replace_word(start, &candidate);
+            return;
+        }
+
+        let (start, candidates) = self.command_candidates(manager);
+
+        match candidates.len() {
+            0 => {},
+            1 => self.replace_word(start, &candidates[0]),
+            _ => {
+                // buffers match anywhere in their name, what was typed is kept when they share less
+                let typed = self.cmdline.text[start..self.cmdline.cursor].iter().collect::<String>();
+                let prefix = common_prefix(&candidates);
+
+                if prefix.starts_with(&typed) {
+                    self.replace_word(start, &prefix);
+                }
+
+                self.cmdline.wildmenu = Some(Wildmenu {
+                    candidates,
+                    selected: None,
+                    start,
+                });
+            },
+        }
+    }
+
+    // the word goes on after the cursor up to the next space that is not escaped
+    fn replace_word(&mut self, start: usize, text: &str) {
+        let cmdline = &mut self.cmdline;
+        let mut end = start;
+
+        while end < cmdline.text.len() && (end < cmdline.cursor || cmdline.text[end] != ' ') {
+            end += if cmdline.text[end] == '\\' { 2 } else { 1 };
+        }
+
+        cmdline.text.drain(start..end.min(cmdline.text.len()));
+        cmdline.cursor = start;
+        cmdline.insert(text);
+    }
+
+    // the keys of the command line, keys with ctrl or alt are left to the editor
+    pub fn command_line_key(&mut self, event: &KeyEvent, manager: &mut BufferManager) -> bool {
+        if event.modifiers.ctrl || event.modifiers.alt || event.modifiers.meta {
+            return false;
+        }
+
+        if event.key != Key::Tab {
+            self.cmdline.wildmenu = None;
+        }
+
+        let cmdline = &mut self.cmdline;
+
+        match event.key {
+            Key::Enter => {
+                let command = cmdline.text();
+                self.close_command_line();
+
+                if command != ":" {
+                    self.cmdline.remember(&command);
+
+                    if let Err(err) = self.command(command, manager) {
+                        self.log(&err.to_string());
+                    }
+                }
+            },
+            Key::Escape => self.close_command_line(),
+            Key::Tab => self.complete_command_line(!event.modifiers.shift, manager),
+            Key::Backspace if cmdline.cursor <= 1 && cmdline.text.len() <= 1 => self.close_command_line(),
+            Key::Backspace if cmdline.cursor > 1 => {
+                cmdline.cursor -= 1;
+                cmdline.text.remove(cmdline.cursor);
+                cmdline.recall = None;
+            },
+            Key::Del if cmdline.cursor < cmdline.text.len() => {
+                cmdline.text.remove(cmdline.cursor);
+                cmdline.recall = None;
+            },
+            Key::ArrowLeft => cmdline.cursor = cmdline.cursor.saturating_sub(1).max(1),
+            Key::ArrowRight => cmdline.cursor = (cmdline.cursor + 1).min(cmdline.text.len()),
+            Key::Home => cmdline.cursor = 1,
+            Key::End => cmdline.cursor = cmdline.text.len(),
+            Key::ArrowUp => cmdline.recall(true),
+            Key::ArrowDown => cmdline.recall(false),
+            Key::Char(character) => cmdline.insert(&character.to_string()),
+            _ => {},
+        }
+
+        self.refresh = true;
+        true
+    }
+
+    // the candidates are shown above the command line, scrolled to keep the selected one in view
+    pub fn render_wildmenu(&self, frame: &mut Frame) {
+        let wildmenu = match (&self.cmdline.wildmenu, self.mode) {
+            (Some(wildmenu), Mode::Command) => wildmenu,
+            _ => return,
+        };
+
+        let y = self.screen.height.saturating_sub(2);
+        let normal = self.theme.style(if self.theme.has("menu") { "menu" } else { "bar" });
+        let selected = self.theme.style(if self.theme.has("menu_selected") { "menu_selected" } else { "mode" });
+
+        let width = |index: usize| wildmenu.candidates[index].chars().count() + 2;
+        let mut first = 0;
+
+        if let Some(selected) = wildmenu.selected {
+            while first < selected && (first..=selected).map(width).sum::<usize>() > self.screen.width {
+                first += 1;
+            }
+        }
+
+        let mut x = 0;
+        for (index, candidate) in wildmenu.candidates.iter().enumerate().skip(first) {
+            if x + width(index) > self.screen.width {
+                break;
+            }
+
+            x = frame.put(x, y, " ", &normal);
+            x = frame.put(x, y, candidate, if wildmenu.selected == Some(index) { &selected } else { &normal });
+            x = frame.put(x, y, " ", &normal);
+        }
+
+        frame.fill(x, y, &normal);
+    }
+}
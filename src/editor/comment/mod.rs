@@ -0,0 +1,103 @@
+use crate::editor::{Editor, Mode, Visual};
+
+fn leading(line: &[char]) -> usize {
+    line.iter().take_while(|character| character.is_whitespace()).count()
+}
+
+fn starts_with(line: &[char], text: &str) -> bool {
+    line[leading(line)..].iter().copied().take(text.chars().count()).eq(text.chars())
+}
+
+fn ends_with(line: &[char], text: &str) -> bool {
+    let end = line.len() - line.iter().rev().take_while(|character| character.is_whitespace()).count();
+    let length = text.chars().count();
+
+    end >= length && line[end - length..end].iter().copied().eq(text.chars())
+}
+
+impl Editor {
+    // the lines are commented when every non blank one is, otherwise they are uncommented
+    fn toggle_line_comment(&mut self, lines: &[usize], leader: &str) {
+        let length = leader.chars().count();
+
+        if lines.iter().all(|y| starts_with(&self.buffer[*y], leader)) {
+            for y in lines {
+                let line = &mut self.buffer[*y];
+                let start = leading(line);
+
+                let end = if line.get(start + length) == Some(&' ') { start + length + 1 } else { start + length };
+                line.drain(start..end);
+                self.changed(*y, 1, 1);
+            }
+        } else {
+            let indentation = lines.iter().map(|y| leading(&self.buffer[*y])).min().unwrap_or(0);
+
+            for y in lines {
+                self.buffer[*y].splice(indentation..indentation, format!("{} ", leader).chars());
+                self.changed(*y, 1, 1);
+            }
+        }
+    }
+
+    fn toggle_block_comment(&mut self, lines: &[usize], open: &str, close: &str) {
+        let (first, last) = match (lines.first(), lines.last()) {
+            (Some(first), Some(last)) => (*first, *last),
+            _ => return,
+        };
+
+        if starts_with(&self.buffer[first], open) && ends_with(&self.buffer[last], close) {
+            let line = &mut self.buffer[last];
+            let end = line.len() - line.iter().rev().take_while(|character| character.is_whitespace()).count();
+            let start = end - close.chars().count();
+
+            let start = if start > 0 && line[start - 1] == ' ' { start - 1 } else { start };
+            line.drain(start..end);
+
+            let line = &mut self.buffer[first];
+            let start = leading(line);
+            let end = start + open.chars().count();
+
+            let end = if line.get(end) == Some(&' ') { end + 1 } else { end };
+            line.drain(start..end);
+        } else {
+            let indentation = lines.iter().map(|y| leading(&self.buffer[*y])).min().unwrap_or(0);
+
+            self.buffer[last].extend(format!(" {}", close).chars());
+            self.buffer[first].splice(indentation..indentation, format!("{} ", open).chars());
+        }
+
+        self.changed(first, last - first + 1, last - first + 1);
+    }
+
+    pub fn toggle_comment(&mut self, start: usize, end: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let lines = (start..=end.min(self.buffer.len() - 1))
+            .filter(|y| self.buffer[*y].iter().any(|character| !character.is_whitespace()))
+            .collect::<Vec<usize>>();
+
+        if let Some(leader) = self.syntax.line_comment.clone() {
+            self.toggle_line_comment(&lines, &leader);
+        } else if let Some((open, close)) = self.syntax.block_comment.clone() {
+            self.toggle_block_comment(&lines, &open, &close);
+        } else {
+            return Err(format!("No comments defined for {}", self.syntax.filetype).into());
+        }
+
+        self.cursor.y = start;
+        self.cursor.x = leading(&self.buffer[start]).min(self.buffer[start].len().saturating_sub(1));
+        self.clamp = self.cursor.x;
+        self.refresh = true;
+
+        Ok(())
+    }
+
+    // in Visual mode the selection is toggled, otherwise the lines of the next motion are
+    pub(super) fn comment_command(&mut self, visual: &Visual) -> Result<(), Box<dyn std::error::Error>> {
+        if self.mode == Mode::Visual {
+            self.mode = Mode::Normal;
+            return self.toggle_comment(visual.y.min(self.cursor.y), visual.y.max(self.cursor.y));
+        }
+
+        self.operator = Some(self.cursor.y);
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+use crate::editor::{Editor, Cursor};
+use crate::editor::buffermanager::BufferManager;
+use crate::editor::render::Frame;
+
+use std::collections::HashSet;
+use std::fs;
+
+const MAX_MENU_HEIGHT: usize = 10;
+
+#[derive(Clone, Debug)]
+pub struct Completion {
+    start: Cursor,
+    end: usize,
+    prefix: String,
+    candidates: Vec<String>,
+    selected: Option<usize>,
+}
+
+fn is_word(character: char) -> bool {
+    character.is_alphanumeric() || character == '_'
+}
+
+fn words(line: &[char]) -> Vec<(usize, String)> {
+    let mut words = Vec::new();
+    let mut x = 0;
+
+    while x < line.len() {
+        let length = line[x..].iter().take_while(|character| is_word(**character)).count();
+
+        if length > 0 {
+            words.push((x, line[x..x + length].iter().collect()));
+        }
+
+        x += length.max(1);
+    }
+
+    words
+}
+
+// every character of the pattern shows up in the word in the same order
+fn fuzzy_match(pattern: &str, word: &str) -> bool {
+    let mut characters = word.chars();
+    pattern.chars().all(|wanted| characters.any(|character| character == wanted))
+}
+
+impl Editor {
+    // words of the current buffer by distance to the cursor, then the other buffers and then the keywords
+    fn candidates(&self, start: &Cursor, prefix: &str, manager: &BufferManager) -> Vec<String> {
+        let fuzzy = self.options.get_str("completeopt").split(',').any(|flag| flag == "fuzzy");
+        let matches = |word: &str| word != prefix && (word.starts_with(prefix) || fuzzy && fuzzy_match(prefix, word));
+
+        let mut found: Vec<(usize, bool, usize, usize, String)> = Vec::new();
+
+        for (y, line) in self.buffer.iter().enumerate() {
+            for (x, word) in words(line) {
+                if y == start.y && x == start.x || !matches(&word) {
+                    continue;
+                }
+
+                found.push((0, !word.starts_with(prefix), y.abs_diff(start.y), x.abs_diff(start.x), word));
+            }
+        }
+
+        // other buffers that were not edited are read from their files
+        for (index, buffer) in manager.buffers.iter().enumerate().filter(|(index, _)| *index != manager.current) {
+            let read: Vec<Vec<char>>;
+            let lines = match buffer.kept() {
+                Some(lines) => lines,
+                None => {
+                    read = fs::read_to_string(&buffer.filename).unwrap_or_default().lines().map(|line| line.chars().collect()).collect();
+                    &read
+                },
+            };
+
+            for line in lines {
+                for (_, word) in words(line) {
+                    if matches(&word) {
+                        found.push((1, !word.starts_with(prefix), index, 0, word));
+                    }
+                }
+            }
+        }
+
+        for keyword in self.syntax.keywords().iter().filter(|keyword| matches(keyword)) {
+            found.push((2, !keyword.starts_with(prefix), 0, 0, keyword.clone()));
+        }
+
+        found.sort();
+
+        let mut seen = HashSet::new();
+        found.into_iter()
+            .map(|(_, _, _, _, word)| word)
+            .filter(|word| seen.insert(word.clone()))
+            .collect()
+    }
+
+    // the start of the word before the cursor and what was typed of it
+    fn completed_word(&self) -> (Cursor, String) {
+        let line = &self.buffer[self.cursor.y];
+        let x = self.cursor.x - line[..self.cursor.x].iter().rev().take_while(|character| is_word(**character)).count();
+
+        (Cursor { x, y: self.cursor.y }, line[x..self.cursor.x].iter().collect())
+    }
+
+    // returns whether the first candidate should be inserted right away
+    fn open_completion(&mut self, start: Cursor, prefix: String, candidates: Vec<String>) -> bool {
+        if candidates.is_empty() {
+            self.completion = None;
+            self.log("No matches found");
+            return false;
+        }
+
+        self.completion = Some(Completion {
+            start,
+            end: self.cursor.x,
+            prefix,
+            candidates,
+            selected: None,
+        });
+        self.refresh = true;
+
+        // with noselect the first key only opens the menu
+        !self.options.get_str("completeopt").split(',').any(|flag| flag == "noselect")
+    }
+
+    // ctrl-n and ctrl-p cycle through the candidates and the typed word
+    pub fn complete(&mut self, forward: bool, manager: &BufferManager) {
+        let current = self.completion.as_ref().map(|completion| completion.start.y == self.cursor.y && completion.end == self.cursor.x).unwrap_or(false);
+
+        if !current {
+            let (start, prefix) = self.completed_word();
+            let candidates = self.candidates(&start, &prefix, manager);
+
+            if !self.open_completion(start, prefix, candidates) {
+                return;
+            }
+        }
+
+        self.select_completion(forward);
+    }
+
+    // candidates from somewhere else, like a language server
+    pub fn complete_with(&mut self, candidates: Vec<String>) {
+        let (start, prefix) = self.completed_word();
+        let fuzzy = self.options.get_str("completeopt").split(',').any(|flag| flag == "fuzzy");
+
+        let candidates = candidates.into_iter()
+            .filter(|candidate| candidate.starts_with(&prefix) || fuzzy && fuzzy_match(&prefix, candidate))
+            .collect();
+
+        if self.open_completion(start, prefix, candidates) {
+            self.select_completion(true);
+        }
+    }
+
+    fn select_completion(&mut self, forward: bool) {
+        let completion = match &mut self.completion {
+            Some(completion) => completion,
+            None => return,
+        };
+
+        let last = completion.candidates.len() - 1;
+        completion.selected = match (completion.selected, forward) {
+            (None, true) => Some(0),
+            (None, false) => Some(last),
+            (Some(selected), true) if selected == last => None,
+            (Some(selected), true) => Some(selected + 1),
+            (Some(0), false) => None,
+            (Some(selected), false) => Some(selected - 1),
+        };
+
+        let word = match completion.selected {
+            Some(selected) => completion.candidates[selected].clone(),
+            None => completion.prefix.clone(),
+        };
+
+        let start = completion.start;
+        self.buffer[start.y].splice(start.x..self.cursor.x, word.chars());
+
+        self.cursor.x = start.x + word.chars().count();
+        completion.end = self.cursor.x;
+
+        self.clamp = self.cursor.x;
+        self.changed(start.y, 1, 1);
+    }
+
+    pub fn close_completion(&mut self) {
+        if self.completion.take().is_some() {
+            self.refresh = true;
+        }
+    }
+
+    // the menu goes under the cursor line, or over it when there is no room left
+    pub fn render_completion(&self, frame: &mut Frame) {
+        let completion = match &self.completion {
+            Some(completion) => completion,
+            None => return,
+        };
+
+        let flags = self.options.get_str("completeopt");
+        let flags = flags.split(',').collect::<Vec<&str>>();
+
+        if !flags.contains(&"menu") || completion.candidates.len() == 1 && !flags.contains(&"menuone") {
+            return;
+        }
+
+        let height = completion.candidates.len().min(MAX_MENU_HEIGHT);
+        let lines = self.screen.height - self.bottom_bar();
+        let row = self.cursor_row();
+
+        let top = if row + 1 + height <= lines {
+            row + 1
+        } else if row >= height {
+            row - height
+        } else {
+            row + 1
+        };
+
+        let width = completion.candidates.iter().map(|word| word.chars().count()).max().unwrap_or(0) + 2;
+        let x = self.gutter() + completion.start.x.saturating_sub(self.screen.x);
+        let x = x.min(self.screen.width.saturating_sub(width));
+
+        // the menu scrolls so the selected word stays in it
+        let first = completion.selected.map(|selected| (selected + 1).saturating_sub(height)).unwrap_or(0);
+
+        let normal = self.theme.style(if self.theme.has("menu") { "menu" } else { "bar" });
+        let selected = self.theme.style(if self.theme.has("menu_selected") { "menu_selected" } else { "mode" });
+
+        for (index, word) in completion.candidates.iter().enumerate().skip(first).take(height) {
+            let y = top + index - first;
+
+            if y >= lines {
+                break;
+            }
+
+            let style = if completion.selected == Some(index) { &selected } else { &normal };
+            frame.put(x, y, &format!(" {:<width$}", word, width = width - 1), style);
+        }
+    }
+}
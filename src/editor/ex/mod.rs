@@ -0,0 +1,731 @@
+use crate::editor::{Editor, Cursor};
+use crate::editor::buffermanager::{BufferManager, NOT_WRITTEN};
+use crate::editor::finder::Source;
+
+use regex::Regex;
+
+use std::path::Path;
+
+// an argument and the character it starts at
+type Argument = (usize, String);
+type Handler = fn(&mut Editor, &Command, &mut BufferManager) -> Result<(), Box<dyn std::error::Error>>;
+
+// the mapping commands take the rest of the line, a `|` in the right hand side is part of it
+const MAP_COMMANDS: [&str; 12] = [
+    "map", "noremap", "unmap", "nmap", "nnoremap", "nunmap", "imap", "inoremap", "iunmap", "vmap", "vnoremap", "vunmap",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arity {
+    Exactly(usize),
+    Between(usize, usize),
+    // the text after the name as it was typed, quotes and all
+    Raw,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Complete {
+    Nothing,
+    File,
+    Option,
+    Buffer,
+}
+
+pub struct Definition {
+    name: &'static str,
+    aliases: &'static [&'static str],
+    arity: Arity,
+    range: bool,
+    bang: bool,
+    // `|` starts the next command
+    bar: bool,
+    complete: Complete,
+    // the arguments before it are not completed, like the pattern of :grep
+    completed: usize,
+    handler: Handler,
+}
+
+impl Definition {
+    fn new(name: &'static str, arity: Arity, handler: Handler) -> Definition {
+        Definition {
+            name,
+            aliases: &[],
+            arity,
+            range: false,
+            bang: false,
+            bar: true,
+            complete: Complete::Nothing,
+            completed: 0,
+            handler,
+        }
+    }
+
+    fn aliases(self, aliases: &'static [&'static str]) -> Definition {
+        Definition { aliases, ..self }
+    }
+
+    fn range(self) -> Definition {
+        Definition { range: true, ..self }
+    }
+
+    fn bang(self) -> Definition {
+        Definition { bang: true, ..self }
+    }
+
+    fn rest(self) -> Definition {
+        Definition { bar: false, ..self }
+    }
+
+    fn complete(self, complete: Complete) -> Definition {
+        Definition { complete, ..self }
+    }
+
+    fn complete_from(self, completed: usize, complete: Complete) -> Definition {
+        Definition { complete, completed, ..self }
+    }
+
+    fn is(&self, name: &str) -> bool {
+        self.name == name || self.aliases.contains(&name)
+    }
+}
+
+#[derive(Debug)]
+pub struct Command {
+    name: String,
+    range: Option<(usize, usize)>,
+    bang: bool,
+    arguments: Vec<String>,
+    raw: String,
+}
+
+impl Command {
+    fn argument(&self) -> &str {
+        self.arguments.first().map(|argument| argument.as_str()).unwrap_or("")
+    }
+
+    // the cursor line when no range was given
+    fn lines(&self, editor: &Editor) -> (usize, usize) {
+        self.range.unwrap_or((editor.cursor.y, editor.cursor.y))
+    }
+}
+
+// every command, a new one only has to be added here
+fn definitions() -> Vec<Definition> {
+    let mut definitions = vec![
+        Definition::new("E", Arity::Exactly(0), |editor, _, _| {
+            editor.log_save();
+            Ok(())
+        }),
+        Definition::new("EQ", Arity::Exactly(0), |editor, _, _| {
+            editor.log_save();
+            editor.quit = true;
+            Ok(())
+        }),
+        Definition::new("w", Arity::Between(0, 1), |editor, command, _| editor.write(command))
+            .range().bang().complete(Complete::File),
+        Definition::new("wq", Arity::Between(0, 1), |editor, command, _| {
+            editor.write(command)?;
+            editor.quit = true;
+            Ok(())
+        }).bang().complete(Complete::File),
+        Definition::new("q", Arity::Exactly(0), |editor, command, manager| {
+            if !command.bang {
+                manager.check_written(editor)?;
+            }
+
+            editor.quit = true;
+            Ok(())
+        }).bang(),
+        Definition::new("qb", Arity::Exactly(0), |editor, command, manager| manager.close_buffer(editor, command.bang))
+            .aliases(&["bd"]).bang(),
+        Definition::new("bn", Arity::Exactly(0), |editor, _, manager| {
+            manager.save_buffer(editor);
+            manager.next_buffer(editor)
+        }),
+        Definition::new("bp", Arity::Exactly(0), |editor, _, manager| {
+            manager.save_buffer(editor);
+            manager.previous_buffer(editor)
+        }),
+        Definition::new("b", Arity::Exactly(1), |editor, command, manager| manager.select(editor, command.argument()))
+            .complete(Complete::Buffer),
+        Definition::new("O", Arity::Exactly(1), |editor, command, manager| manager.add(editor, command.argument()))
+            .complete(Complete::File),
+        Definition::new("e", Arity::Between(0, 1), |editor, command, manager| {
+            if command.arguments.is_empty() {
+                if editor.modified && !command.bang {
+                    return Err(NOT_WRITTEN.into());
+                }
+
+                // the file is read again, the cursor stays where it was
+                return manager.revert(editor);
+            }
+
+            manager.open(editor, command.argument())
+        }).bang().complete(Complete::File),
+        Definition::new("set", Arity::Raw, |editor, command, _| {
+            let message = editor.options.set(&command.raw, command.name == "setlocal")?;
+            editor.update_folds();
+            editor.log(&message);
+            Ok(())
+        }).aliases(&["setlocal"]).complete(Complete::Option),
+        Definition::new("source", Arity::Between(0, 1), |editor, command, manager| editor.source(command.argument(), manager))
+            .complete(Complete::File),
+        Definition::new("colorscheme", Arity::Between(0, 1), |editor, command, _| editor.colorscheme(command.argument())),
+        Definition::new("node", Arity::Exactly(0), |editor, _, _| {
+            let node = editor.syntax.node_at(editor.cursor.y, editor.cursor.x).unwrap_or_else(|| String::from("No syntax tree"));
+            editor.log(&node);
+            Ok(())
+        }),
+        Definition::new("renderstats", Arity::Exactly(0), |editor, _, _| {
+            let stats = editor.renderer.stats();
+            editor.log(&stats);
+            Ok(())
+        }),
+        Definition::new("rename", Arity::Exactly(1), |editor, command, _| editor.rename(command.argument())),
+        Definition::new("format", Arity::Exactly(0), |editor, _, _| editor.format()),
+        Definition::new("codeaction", Arity::Exactly(0), |editor, _, _| editor.code_actions()),
+        Definition::new("diagnostics", Arity::Exactly(0), |editor, _, manager| editor.list_diagnostics(manager)),
+        Definition::new("lsp", Arity::Exactly(0), |editor, _, _| {
+            let status = editor.lsp_status();
+            editor.log(&status);
+            Ok(())
+        }),
+        Definition::new("grep", Arity::Between(1, 2), |editor, command, manager| {
+            let root = command.arguments.get(1).map(|root| root.as_str()).unwrap_or(".");
+            editor.grep(command.argument(), root, manager)
+        }).complete_from(1, Complete::File),
+        Definition::new("files", Arity::Exactly(0), |editor, _, manager| {
+            editor.open_finder(Source::Files, manager);
+            Ok(())
+        }),
+        Definition::new("buffers", Arity::Exactly(0), |editor, _, manager| {
+            editor.open_finder(Source::Buffers, manager);
+            Ok(())
+        }),
+        Definition::new("recent", Arity::Exactly(0), |editor, _, manager| {
+            editor.open_finder(Source::Recent, manager);
+            Ok(())
+        }),
+        Definition::new("symbols", Arity::Exactly(0), |editor, _, manager| {
+            editor.open_finder(Source::Symbols, manager);
+            Ok(())
+        }),
+        Definition::new("make", Arity::Raw, |editor, command, _| editor.make(&command.raw)),
+        Definition::new("cn", Arity::Exactly(0), |editor, _, manager| editor.next_quickfix(true, manager)),
+        Definition::new("cp", Arity::Exactly(0), |editor, _, manager| editor.next_quickfix(false, manager)),
+        Definition::new("copen", Arity::Exactly(0), |editor, _, manager| editor.open_quickfix(manager)),
+        Definition::new("create", Arity::Exactly(1), |editor, command, _| editor.create_entry(command.argument())),
+        Definition::new("move", Arity::Exactly(1), |editor, command, _| editor.transfer_entry(command.argument(), false)),
+        Definition::new("copy", Arity::Exactly(1), |editor, command, _| editor.transfer_entry(command.argument(), true)),
+        Definition::new("delete", Arity::Exactly(0), |editor, _, _| editor.delete_entry()),
+        Definition::new("trust", Arity::Exactly(0), |editor, _, manager| editor.trust(manager)),
+        Definition::new("d", Arity::Exactly(0), |editor, command, _| {
+            if !editor.modifiable() {
+                return Ok(());
+            }
+
+            let (start, end) = command.lines(editor);
+            editor.delete_lines(start, end);
+            Ok(())
+        }).range(),
+        Definition::new("y", Arity::Exactly(0), |editor, command, _| {
+            let (start, end) = command.lines(editor);
+            editor.clipboard = editor.buffer[start..=end].to_vec();
+            editor.log(&format!("{} lines yanked", end - start + 1));
+            Ok(())
+        }).range(),
+        Definition::new(">", Arity::Exactly(0), |editor, command, _| {
+            if !editor.modifiable() {
+                return Ok(());
+            }
+
+            let (start, end) = command.lines(editor);
+            editor.indent_lines(&command.name, start, end);
+            Ok(())
+        }).aliases(&["<"]).range(),
+        Definition::new("fold", Arity::Exactly(0), |editor, command, _| {
+            if editor.options.get_str("foldmethod") != "manual" {
+                return Err("Cannot create a fold with the current foldmethod".into());
+            }
+
+            let (start, end) = command.lines(editor);
+            editor.folds.add(start, end);
+            editor.refresh = true;
+            Ok(())
+        }).range(),
+        Definition::new("mark", Arity::Exactly(1), |editor, command, _| {
+            let mark = match command.argument().chars().collect::<Vec<char>>()[..] {
+                [mark] if mark.is_ascii_lowercase() => mark,
+                _ => return Err("A mark is a letter from a to z".into()),
+            };
+
+            editor.marks.insert(mark, command.lines(editor).1);
+            editor.mark_signs();
+            Ok(())
+        }).range(),
+    ];
+
+    for name in MAP_COMMANDS {
+        definitions.push(Definition::new(name, Arity::Raw, |editor, command, _| {
+            let message = editor.keymap.execute(&command.name, &command.raw, &editor.options.get_str("mapleader"))?;
+            editor.log(&message);
+            Ok(())
+        }).rest());
+    }
+
+    definitions
+}
+
+pub fn names() -> Vec<&'static str> {
+    definitions().iter()
+        .flat_map(|definition| std::iter::once(definition.name).chain(definition.aliases.iter().copied()))
+        .filter(|name| name.chars().all(char::is_alphabetic))
+        .collect()
+}
+
+// what the argument at the index of the command at the start of the line is completed with
+pub fn completion(line: &str, argument: usize) -> Complete {
+    let line = line.trim_start_matches(':');
+    let name = line.trim_start_matches(|character: char| !character.is_alphabetic())
+        .split(|character: char| !character.is_alphabetic())
+        .next()
+        .unwrap_or("");
+
+    definitions().iter()
+        .find(|definition| definition.is(name))
+        .filter(|definition| argument >= definition.completed)
+        .map(|definition| definition.complete)
+        .unwrap_or(Complete::Nothing)
+}
+
+// the other way around, for text that is put into a command line
+pub fn escape(text: &str) -> String {
+    text.chars()
+        .flat_map(|character| {
+            let escaped = matches!(character, ' ' | '"' | '\'' | '|' | '\\');
+            escaped.then_some('\\').into_iter().chain(std::iter::once(character))
+        })
+        .collect()
+}
+
+// the command ends at the first `|` that is not quoted or escaped, `\|` is left for the arguments
+fn split_bar(text: &str) -> (&str, Option<&str>) {
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (index, character) in text.char_indices() {
+        match (character, quote) {
+            _ if escaped => escaped = false,
+            ('\\', Some('\'')) => {},
+            ('\\', _) => escaped = true,
+            ('"' | '\'', None) => quote = Some(character),
+            (_, Some(open)) if character == open => quote = None,
+            ('|', None) => return (&text[..index], Some(&text[index + 1..])),
+            _ => {},
+        }
+    }
+
+    (text, None)
+}
+
+// arguments are split at spaces, quotes keep them together and a backslash escapes a space,
+// quote, bar or backslash, other backslashes are kept for patterns
+fn split_arguments(text: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let (mut arguments, last, quote) = tokenize(text);
+
+    if quote.is_some() {
+        return Err("Missing closing quote".into());
+    }
+
+    arguments.extend(last);
+    Ok(arguments.into_iter().map(|(_, argument)| argument).collect())
+}
+
+// the argument the text ends in without its escapes, where it starts and how many come before it,
+// for completing it
+pub fn last_argument(text: &str) -> (usize, usize, String) {
+    let (arguments, last, _) = tokenize(text);
+    let (start, argument) = last.unwrap_or((text.chars().count(), String::new()));
+
+    (start, arguments.len(), argument)
+}
+
+// the finished arguments with the character they start at, the one the text ends in and the
+// quote that is still open
+fn tokenize(text: &str) -> (Vec<Argument>, Option<Argument>, Option<char>) {
+    let mut arguments = Vec::new();
+    let mut argument: Option<Argument> = None;
+    let mut quote = None;
+    let mut characters = text.chars().enumerate().peekable();
+
+    while let Some((index, character)) = characters.next() {
+        match (character, quote) {
+            ('\\', Some('\'')) => argument.get_or_insert((index, String::new())).1.push(character),
+            ('\\', _) => {
+                let escaped = characters.next_if(|(_, next)| matches!(next, ' ' | '"' | '\'' | '|' | '\\'));
+                let current = argument.get_or_insert((index, String::new()));
+
+                match escaped {
+                    Some((_, escaped)) => current.1.push(escaped),
+                    None => current.1.push(character),
+                }
+            },
+            ('"' | '\'', None) => {
+                quote = Some(character);
+                argument.get_or_insert((index, String::new()));
+            },
+            (_, Some(open)) if character == open => quote = None,
+            (_, None) if character.is_whitespace() => arguments.extend(argument.take()),
+            _ => argument.get_or_insert((index, String::new())).1.push(character),
+        }
+    }
+
+    (arguments, argument, quote)
+}
+
+struct Parser<'a> {
+    editor: &'a Editor,
+    characters: Vec<char>,
+    position: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.characters.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let start = self.position;
+
+        while self.peek().is_some_and(|character| character.is_ascii_digit()) {
+            self.position += 1;
+        }
+
+        self.characters[start..self.position].iter().collect::<String>().parse().ok()
+    }
+
+    fn last(&self) -> usize {
+        self.editor.buffer.len() - 1
+    }
+
+    // the next line after `from` that matches, searching wraps around the end of the buffer
+    fn pattern(&mut self, delimiter: char, from: usize) -> Result<usize, Box<dyn std::error::Error>> {
+        let start = self.position;
+
+        while let Some(character) = self.peek() {
+            if character == delimiter && self.characters.get(self.position.wrapping_sub(1)) != Some(&'\\') {
+                break;
+            }
+            self.position += 1;
+        }
+
+        let pattern = self.characters[start..self.position].iter().collect::<String>();
+        self.position += 1;
+
+        let regex = Regex::new(&pattern)?;
+        let count = self.editor.buffer.len();
+
+        let lines = (1..=count).map(|offset| if delimiter == '/' { (from + offset) % count } else { (from + count * 2 - offset) % count });
+        let found = lines.into_iter().find(|y| regex.is_match(&self.editor.buffer[*y].iter().collect::<String>()));
+
+        found.ok_or_else(|| format!("Pattern not found: `{}`", pattern).into())
+    }
+
+    // a line number, `.`, `$`, a mark or a pattern followed by any number of offsets
+    fn address(&mut self, current: usize) -> Result<Option<usize>, Box<dyn std::error::Error>> {
+        let base = match self.peek() {
+            Some('.') => {
+                self.position += 1;
+                Some(current as isize)
+            },
+            Some('$') => {
+                self.position += 1;
+                Some(self.last() as isize)
+            },
+            Some(character) if character.is_ascii_digit() => self.number().map(|line| line.max(1) as isize - 1),
+            Some('\'') => {
+                let mark = self.characters.get(self.position + 1).copied().ok_or("Missing mark name")?;
+                self.position += 2;
+
+                let line = self.editor.marks.get(&mark).ok_or_else(|| format!("Mark not set: `'{}`", mark))?;
+                Some(*line as isize)
+            },
+            Some(delimiter @ ('/' | '?')) => {
+                self.position += 1;
+                Some(self.pattern(delimiter, current)? as isize)
+            },
+            Some('+' | '-') => Some(current as isize),
+            _ => None,
+        };
+
+        let mut line = match base {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+
+        while let Some(sign @ ('+' | '-')) = self.peek() {
+            self.position += 1;
+            let offset = self.number().unwrap_or(1) as isize;
+
+            line += if sign == '+' { offset } else { -offset };
+        }
+
+        if line < 0 || line as usize > self.last() {
+            return Err("Invalid range".into());
+        }
+
+        Ok(Some(line as usize))
+    }
+
+    // `%`, one address or two split by `,` or by `;` which moves to the first before the second
+    fn range(&mut self) -> Result<Option<(usize, usize)>, Box<dyn std::error::Error>> {
+        let current = self.editor.cursor.y;
+
+        if self.peek() == Some('%') {
+            self.position += 1;
+            return Ok(Some((0, self.last())));
+        }
+
+        let first = self.address(current)?;
+
+        let separator = match self.peek() {
+            Some(separator @ (',' | ';')) => separator,
+            _ => return Ok(first.map(|line| (line, line))),
+        };
+
+        self.position += 1;
+
+        let start = first.unwrap_or(current);
+        let end = self.address(if separator == ';' { start } else { current })?.unwrap_or(current);
+
+        Ok(Some((start.min(end), start.max(end))))
+    }
+
+    // a word or a single character like `>`
+    fn name(&mut self) -> String {
+        let start = self.position;
+
+        while self.peek().is_some_and(char::is_alphabetic) {
+            self.position += 1;
+        }
+
+        if self.position == start && self.peek().is_some_and(|character| !character.is_whitespace() && character != '|') {
+            self.position += 1;
+        }
+
+        self.characters[start..self.position].iter().collect()
+    }
+
+    fn rest(&self) -> String {
+        self.characters[self.position..].iter().collect()
+    }
+}
+
+// `:/text` searches like before, a pattern is only an address when something follows it
+fn is_search(line: &str) -> bool {
+    let pattern = match line.strip_prefix('/') {
+        Some(pattern) => pattern,
+        None => return false,
+    };
+
+    match pattern.find('/') {
+        Some(end) => pattern[end + 1..].trim().is_empty(),
+        None => true,
+    }
+}
+
+impl Editor {
+    // runs a line of commands split by `|`, the first error stops the rest
+    pub fn command(&mut self, cmd: String, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        let mut next = Some(cmd);
+
+        while let Some(line) = next.take() {
+            let line = line.trim_start_matches(|character: char| character == ':' || character.is_whitespace());
+
+            if is_search(line) {
+                self.search(line[1..].trim_end_matches('/'));
+                return Ok(());
+            }
+
+            let registry = definitions();
+            let mut parser = Parser {
+                editor: self,
+                characters: line.chars().collect(),
+                position: 0,
+            };
+
+            let range = parser.range()?;
+            parser.skip_whitespace();
+
+            let name = parser.name();
+            let bang = parser.peek() == Some('!');
+            if bang {
+                parser.position += 1;
+            }
+
+            let rest = parser.rest();
+
+            if name.is_empty() {
+                // a range on its own goes to its last line
+                if let Some((_, end)) = range {
+                    self.goto(Cursor { x: 0, y: end });
+                }
+
+                next = rest.trim().strip_prefix('|').map(String::from);
+                continue;
+            }
+
+            let definition = registry.iter()
+                .find(|definition| definition.is(&name))
+                .ok_or_else(|| format!("Unknown command: `:{}`", line.trim()))?;
+
+            let (text, remainder) = if definition.bar { split_bar(&rest) } else { (rest.as_str(), None) };
+
+            if !rest.is_empty() && !rest.starts_with(char::is_whitespace) && !rest.starts_with('|') {
+                return Err(format!("Unknown command: `:{}`", line.trim()).into());
+            }
+
+            if range.is_some() && !definition.range {
+                return Err(format!("No range allowed: `:{}`", name).into());
+            }
+
+            if bang && !definition.bang {
+                return Err(format!("No ! allowed: `:{}`", name).into());
+            }
+
+            let arguments = if definition.arity == Arity::Raw { Vec::new() } else { split_arguments(text)? };
+
+            let (min, max) = match definition.arity {
+                Arity::Exactly(count) => (count, count),
+                Arity::Between(min, max) => (min, max),
+                Arity::Raw => (0, usize::MAX),
+            };
+
+            if arguments.len() < min {
+                return Err(format!("Argument required: `:{}`", name).into());
+            }
+
+            if arguments.len() > max {
+                return Err(format!("Too many arguments: `:{}`", line.trim()).into());
+            }
+
+            let command = Command {
+                raw: if definition.bar { text.replace("\\|", "|").trim().to_string() } else { text.trim().to_string() },
+                name,
+                range,
+                bang,
+                arguments,
+            };
+
+            (definition.handler)(self, &command, manager)?;
+            next = remainder.map(String::from);
+        }
+
+        Ok(())
+    }
+
+    // :w writes the buffer, a file name writes it or the lines of the range somewhere else
+    fn write(&mut self, command: &Command) -> Result<(), Box<dyn std::error::Error>> {
+        let path = command.argument();
+
+        if path.is_empty() {
+            if command.range.is_some() {
+                return Err("Writing part of the buffer needs a file name".into());
+            }
+
+            if !self.log_save() {
+                return Err(format!("failed to write to `{}`", self.filename).into());
+            }
+
+            return Ok(());
+        }
+
+        if Path::new(path).exists() && !command.bang && !self.is_current(path) {
+            return Err(format!("`{}` exists, add ! to write over it", path).into());
+        }
+
+        let (start, end) = command.range.unwrap_or((0, self.buffer.len() - 1));
+
+        self.write_lines(path, start, end)?;
+        self.log(&format!("wrote {} lines to `{}`", end - start + 1, path));
+
+        if command.range.is_none() && self.is_current(path) {
+            self.modified = false;
+        }
+
+        Ok(())
+    }
+
+    fn is_current(&self, path: &str) -> bool {
+        match (std::fs::canonicalize(path), std::fs::canonicalize(&self.filename)) {
+            (Ok(path), Ok(current)) => path == current,
+            _ => false,
+        }
+    }
+
+    fn delete_lines(&mut self, start: usize, end: usize) {
+        self.clipboard = self.buffer.drain(start..=end).collect();
+        self.changed(start, end - start + 1, 0);
+
+        if self.buffer.is_empty() {
+            self.buffer.push(Vec::new());
+            self.changed(0, 0, 1);
+        }
+
+        let count = end - start + 1;
+        self.goto(Cursor { x: 0, y: start });
+        self.log(&format!("{} fewer lines", count));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_argument_keeps_escaped_spaces() {
+        assert_eq!(last_argument("one my\\ fi"), (4, 1, String::from("my fi")));
+        assert_eq!(last_argument("'a b' \"c d"), (6, 1, String::from("c d")));
+        assert_eq!(last_argument("one "), (4, 1, String::new()));
+        assert_eq!(last_argument(""), (0, 0, String::new()));
+    }
+
+    #[test]
+    fn escaped_arguments_split_back() {
+        let name = "my file's | name\\";
+
+        assert_eq!(split_arguments(&format!("{} x", escape(name))).unwrap(), vec![name, "x"]);
+    }
+
+    #[test]
+    fn edits_are_not_lost_without_a_bang() {
+        let mut editor = Editor::new("test.txt").unwrap();
+        editor.buffer = vec![Vec::new()];
+        let mut manager = BufferManager::new(&editor);
+
+        editor.insert('x');
+        for command in [":q", ":bd", ":e"] {
+            let err = editor.command(command.to_string(), &mut manager).unwrap_err();
+            assert_eq!(err.to_string(), NOT_WRITTEN);
+        }
+        assert!(!editor.quit);
+
+        // the other buffers are kept until quitting
+        manager.save_buffer(&editor);
+        editor.modified = false;
+        manager.load_buffer(&editor);
+        manager.current = 1;
+
+        let err = editor.command(String::from(":q"), &mut manager).unwrap_err();
+        assert_eq!(err.to_string(), "No write since last change of `*New Buffer*` (add ! to override)");
+
+        editor.command(String::from(":q!"), &mut manager).unwrap();
+        assert!(editor.quit);
+    }
+}
@@ -0,0 +1,589 @@
+use crate::editor::{Editor, Cursor, config_path, write_config};
+use crate::editor::buffermanager::BufferManager;
+use crate::editor::grep::{display, is_binary};
+use crate::editor::input::{Key, KeyEvent, Modifiers};
+use crate::editor::render::Frame;
+use crate::editor::theme::Style;
+
+use ignore::WalkBuilder;
+use regex::Regex;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+
+const INDEX_BATCH: usize = 1000;
+const MAX_RECENT: usize = 100;
+const MIN_PREVIEW_WIDTH: usize = 60;
+
+// lines that define something in most languages, used when no language server lists the symbols
+const SYMBOL_PATTERN: &str = r"^\s*(?:(?:pub(?:\([^)]*\))?|export|async|unsafe|static|default)\s+)*(fn|struct|enum|trait|impl|mod|type|const|macro_rules!|class|def|function|interface)(?:<[^>]*>)?\s+([A-Za-z_][A-Za-z0-9_]*)";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Source {
+    Files,
+    Buffers,
+    Recent,
+    Symbols,
+}
+
+impl Source {
+    fn title(&self) -> &'static str {
+        match self {
+            Source::Files => "Files",
+            Source::Buffers => "Buffers",
+            Source::Recent => "Recent",
+            Source::Symbols => "Symbols",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Item {
+    label: String,
+    filename: String,
+    cursor: Cursor,
+    // open buffers are switched to instead of opened again
+    buffer: Option<usize>,
+}
+
+impl Item {
+    pub fn new(label: String, filename: &str, cursor: Cursor) -> Item {
+        Item {
+            label,
+            filename: filename.to_string(),
+            cursor,
+            buffer: None,
+        }
+    }
+}
+
+pub struct Finder {
+    source: Source,
+    items: Vec<Item>,
+    query: String,
+    // the matching items with the positions of the matched characters, best first
+    matches: Vec<(i64, usize, Vec<usize>)>,
+    selected: usize,
+    first: usize,
+    index: Option<Receiver<Vec<String>>>,
+    // the file and how many of its first lines were read
+    preview: Option<(String, usize, Vec<String>)>,
+}
+
+fn is_boundary(previous: Option<char>, character: char) -> bool {
+    match previous {
+        None => true,
+        Some(previous) => "/\\_-. ".contains(previous) || previous.is_lowercase() && character.is_uppercase(),
+    }
+}
+
+// the query has to show up in the text in order, matches at the start of words, right after each
+// other and in the file name score higher while gaps cost a little
+fn score(query: &[char], text: &str) -> Option<(i64, Vec<usize>)> {
+    if query.is_empty() {
+        return Some((0, Vec::new()));
+    }
+
+    let characters = text.chars().collect::<Vec<char>>();
+    let ignore_case = !query.iter().any(|character| character.is_uppercase());
+
+    let equal = |a: char, b: char| if ignore_case { a.to_lowercase().eq(b.to_lowercase()) } else { a == b };
+
+    // the first end of a match and then the last start before it gives the tightest window
+    let mut end = 0;
+    let mut wanted = 0;
+
+    for (index, character) in characters.iter().enumerate() {
+        if wanted < query.len() && equal(*character, query[wanted]) {
+            wanted += 1;
+            end = index;
+        }
+    }
+
+    if wanted < query.len() {
+        return None;
+    }
+
+    let mut start = end;
+    let mut wanted = query.len();
+
+    for index in (0..=end).rev() {
+        if wanted > 0 && equal(characters[index], query[wanted - 1]) {
+            wanted -= 1;
+            start = index;
+        }
+    }
+
+    let basename = characters.iter().rposition(|character| *character == '/').map(|index| index + 1).unwrap_or(0);
+    let mut positions = Vec::new();
+    let mut score = 0;
+    let mut wanted = 0;
+
+    for (index, character) in characters.iter().enumerate().take(end + 1).skip(start) {
+        if wanted == query.len() || !equal(*character, query[wanted]) {
+            continue;
+        }
+
+        score += 16;
+
+        if positions.last() == Some(&index.wrapping_sub(1)) {
+            score += 12;
+        } else if let Some(last) = positions.last() {
+            score -= (index - last - 1).min(8) as i64;
+        }
+
+        if is_boundary(index.checked_sub(1).map(|previous| characters[previous]), *character) {
+            score += 10;
+        }
+
+        if index >= basename {
+            score += 4;
+        }
+
+        positions.push(index);
+        wanted += 1;
+    }
+
+    Some((score - characters.len() as i64 / 8, positions))
+}
+
+// the files of the working directory that git does not ignore, sent in batches from another thread
+fn index(waker: Sender<Vec<u8>>) -> Receiver<Vec<String>> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut batch = Vec::new();
+
+        for entry in WalkBuilder::new(".").build().flatten() {
+            if !entry.file_type().map(|kind| kind.is_file()).unwrap_or(false) {
+                continue;
+            }
+
+            batch.push(display(entry.path()));
+
+            if batch.len() == INDEX_BATCH {
+                if sender.send(std::mem::take(&mut batch)).is_err() {
+                    return;
+                }
+                let _ = waker.send(Vec::new());
+            }
+        }
+
+        let _ = sender.send(batch);
+        let _ = waker.send(Vec::new());
+    });
+
+    receiver
+}
+
+fn recent_files() -> Vec<String> {
+    config_path("recent").ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .unwrap_or_default()
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+// the most recent file goes first
+pub fn remember_file(filename: &str) {
+    let path = match fs::canonicalize(filename) {
+        Ok(path) => path.to_string_lossy().to_string(),
+        Err(_) => return,
+    };
+
+    let mut recent = recent_files();
+    if recent.first() == Some(&path) {
+        return;
+    }
+
+    recent.retain(|known| *known != path);
+    recent.insert(0, path);
+    recent.truncate(MAX_RECENT);
+
+    let _ = write_config("recent", &(recent.join("\n") + "\n"));
+}
+
+// the first lines of the file, binary files are not shown
+fn preview(filename: &str, limit: usize) -> Vec<String> {
+    let mut reader = match File::open(filename) {
+        Ok(file) => BufReader::new(file),
+        Err(_) => return Vec::new(),
+    };
+
+    if reader.fill_buf().map(is_binary).unwrap_or(false) {
+        return vec![String::from("Binary file")];
+    }
+
+    reader.split(b'\n')
+        .take(limit)
+        .map_while(Result::ok)
+        .map(|line| String::from_utf8_lossy(&line).trim_end_matches('\r').replace('\t', "    "))
+        .collect()
+}
+
+impl Finder {
+    fn new(source: Source, items: Vec<Item>) -> Finder {
+        let mut finder = Finder {
+            source,
+            items: Vec::new(),
+            query: String::new(),
+            matches: Vec::new(),
+            selected: 0,
+            first: 0,
+            index: None,
+            preview: None,
+        };
+
+        finder.add(items);
+        finder
+    }
+
+    fn add(&mut self, items: Vec<Item>) {
+        let query = self.query.chars().collect::<Vec<char>>();
+        let start = self.items.len();
+
+        self.items.extend(items);
+
+        for index in start..self.items.len() {
+            if let Some((score, positions)) = score(&query, &self.items[index].label) {
+                self.matches.push((score, index, positions));
+            }
+        }
+
+        self.sort();
+    }
+
+    // without a query the items keep their order
+    fn sort(&mut self) {
+        self.matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    }
+
+    fn filter(&mut self) {
+        let items = std::mem::take(&mut self.items);
+
+        self.matches.clear();
+        self.add(items);
+
+        self.selected = 0;
+        self.first = 0;
+    }
+
+    // a longer query only matches items the shorter one matched
+    fn narrow(&mut self) {
+        let query = self.query.chars().collect::<Vec<char>>();
+        let matches = std::mem::take(&mut self.matches);
+
+        self.matches = matches.into_iter()
+            .filter_map(|(_, index, _)| score(&query, &self.items[index].label).map(|(score, positions)| (score, index, positions)))
+            .collect();
+        self.sort();
+
+        self.selected = 0;
+        self.first = 0;
+    }
+
+    fn selected(&self) -> Option<&Item> {
+        self.matches.get(self.selected).map(|(_, index, _)| &self.items[*index])
+    }
+}
+
+impl Editor {
+    pub fn open_finder(&mut self, source: Source, manager: &BufferManager) {
+        let items = match source {
+            Source::Files => Vec::new(),
+            Source::Buffers => manager.buffers.iter().enumerate()
+                .map(|(index, buffer)| Item {
+                    label: buffer.filename.clone(),
+                    filename: buffer.filename.clone(),
+                    cursor: Cursor::new(),
+                    buffer: Some(index),
+                })
+                .collect(),
+            Source::Recent => {
+                let directory = env::current_dir().map(|directory| directory.to_string_lossy().to_string() + "/").unwrap_or_default();
+
+                recent_files().into_iter()
+                    .filter(|path| Path::new(path).is_file())
+                    .map(|path| {
+                        let label = path.strip_prefix(&directory).unwrap_or(&path).to_string();
+                        Item::new(label, &path, Cursor::new())
+                    })
+                    .collect()
+            },
+            Source::Symbols => self.symbols(),
+        };
+
+        let mut finder = Finder::new(source, items);
+
+        if source == Source::Files {
+            finder.index = Some(index(self.input.waker()));
+        }
+
+        self.finder = Some(finder);
+
+        // a language server knows the symbols better, its answer replaces the guesses
+        if source == Source::Symbols {
+            if let Err(err) = self.document_symbols() {
+                self.log(&err.to_string());
+            }
+        }
+
+        self.update_preview();
+        self.refresh = true;
+    }
+
+    fn symbols(&self) -> Vec<Item> {
+        let regex = match Regex::new(SYMBOL_PATTERN) {
+            Ok(regex) => regex,
+            Err(_) => return Vec::new(),
+        };
+
+        self.buffer.iter().enumerate()
+            .filter_map(|(y, line)| {
+                let line = line.iter().collect::<String>();
+                let captures = regex.captures(&line)?;
+                let name = captures.get(2)?;
+
+                let cursor = Cursor { x: line[..name.start()].chars().count(), y };
+                Some(Item::new(format!("{} {}", &captures[1], name.as_str()), &self.filename, cursor))
+            })
+            .collect()
+    }
+
+    // the symbols of a language server, dropped when the finder moved on or there are none
+    pub fn finder_symbols(&mut self, items: Vec<Item>) {
+        if items.is_empty() {
+            return;
+        }
+
+        if let Some(finder) = self.finder.as_mut().filter(|finder| finder.source == Source::Symbols) {
+            finder.items.clear();
+            finder.matches.clear();
+            finder.add(items);
+
+            self.update_preview();
+            self.refresh = true;
+        }
+    }
+
+    pub fn finder_poll(&mut self) {
+        let finder = match self.finder.as_mut() {
+            Some(finder) => finder,
+            None => return,
+        };
+
+        let mut found = Vec::new();
+
+        while let Some(receiver) = &finder.index {
+            match receiver.try_recv() {
+                Ok(batch) => found.extend(batch),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => finder.index = None,
+            }
+        }
+
+        if !found.is_empty() {
+            finder.add(found.into_iter().map(|path| Item::new(path.clone(), &path, Cursor::new())).collect());
+            self.update_preview();
+            self.refresh = true;
+        }
+    }
+
+    fn close_finder(&mut self) {
+        self.finder = None;
+        self.refresh = true;
+    }
+
+    // the file of the selected item is read up to the lines that are shown, the current buffer is shown as it is
+    fn update_preview(&mut self) {
+        let (filename, limit) = match self.finder.as_ref().and_then(|finder| finder.selected()) {
+            Some(item) => (item.filename.clone(), item.cursor.y + self.screen.height),
+            None => return,
+        };
+
+        let cached = self.finder.as_ref().and_then(|finder| finder.preview.as_ref()).map(|(known, read, _)| *known == filename && *read >= limit).unwrap_or(false);
+        if cached || filename == self.filename {
+            return;
+        }
+
+        let lines = preview(&filename, limit);
+
+        if let Some(finder) = self.finder.as_mut() {
+            finder.preview = Some((filename, limit, lines));
+        }
+    }
+
+    fn open_item(&mut self, item: Item, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        match item.buffer {
+            Some(index) if index == manager.current => {},
+            Some(index) => {
+                manager.save_buffer(self);
+                manager.current = index;
+                manager.reload(self)?;
+            },
+            None => {
+                manager.open(self, &item.filename)?;
+                self.goto(item.cursor);
+            },
+        }
+
+        Ok(())
+    }
+
+    // every key goes to the finder while it is open
+    pub fn finder_key(&mut self, event: &KeyEvent, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        let height = self.finder_height();
+        let finder = match self.finder.as_mut() {
+            Some(finder) => finder,
+            None => return Ok(()),
+        };
+
+        let ctrl = event.modifiers == Modifiers::CTRL;
+
+        match &event.key {
+            Key::Escape => {
+                self.close_finder();
+                return Ok(());
+            },
+            Key::Enter => {
+                let item = finder.selected().cloned();
+                self.close_finder();
+
+                return match item {
+                    Some(item) => self.open_item(item, manager),
+                    None => Ok(()),
+                };
+            },
+            Key::ArrowUp => finder.selected = finder.selected.saturating_sub(1),
+            Key::Char('p') | Key::Char('k') if ctrl => finder.selected = finder.selected.saturating_sub(1),
+            Key::ArrowDown => finder.selected = (finder.selected + 1).min(finder.matches.len().saturating_sub(1)),
+            Key::Char('n') | Key::Char('j') if ctrl => finder.selected = (finder.selected + 1).min(finder.matches.len().saturating_sub(1)),
+            Key::Backspace => {
+                finder.query.pop();
+                finder.filter();
+            },
+            Key::Char(character) if !event.modifiers.ctrl && !event.modifiers.alt && !event.modifiers.meta => {
+                finder.query.push(*character);
+                finder.narrow();
+            },
+            _ => {},
+        }
+
+        // the selected item stays in view
+        if finder.selected < finder.first {
+            finder.first = finder.selected;
+        } else if finder.selected >= finder.first + height {
+            finder.first = finder.selected + 1 - height;
+        }
+
+        self.update_preview();
+        self.refresh = true;
+        Ok(())
+    }
+
+    pub fn finder_paste(&mut self, text: &str) {
+        if let Some(finder) = self.finder.as_mut() {
+            finder.query.extend(text.chars().filter(|character| !character.is_control()));
+            finder.narrow();
+
+            self.update_preview();
+            self.refresh = true;
+        }
+    }
+
+    // the number of items that fit under the prompt
+    fn finder_height(&self) -> usize {
+        (self.screen.height - self.bottom_bar()).saturating_sub(3).max(1)
+    }
+
+    // the position of the cursor at the end of the prompt
+    pub fn finder_cursor(&self) -> Option<(usize, usize)> {
+        let finder = self.finder.as_ref()?;
+        Some((2 + finder.source.title().len() + 3 + finder.query.chars().count(), 1))
+    }
+
+    // the prompt and the matches on the left, the file of the selected one on the right
+    pub fn render_finder(&self, frame: &mut Frame) {
+        let finder = match &self.finder {
+            Some(finder) => finder,
+            None => return,
+        };
+
+        let height = self.finder_height();
+        let width = self.screen.width.saturating_sub(4);
+        let list_width = if width >= MIN_PREVIEW_WIDTH { width / 2 } else { width };
+
+        let normal = self.theme.style(if self.theme.has("menu") { "menu" } else { "bar" });
+        let selected = self.theme.style(if self.theme.has("menu_selected") { "menu_selected" } else { "mode" });
+
+        // prompt
+        let count = format!("{}/{}{} ", finder.matches.len(), finder.items.len(), if finder.index.is_some() { "..." } else { "" });
+        let prompt = format!(" {}> {}", finder.source.title(), finder.query);
+        let padding = list_width.saturating_sub(prompt.chars().count() + count.len());
+        frame.put(2, 1, &format!("{}{}{}", prompt, " ".repeat(padding), count).chars().take(list_width).collect::<String>(), &normal);
+
+        // matches
+        for row in 0..height {
+            let y = row + 2;
+            let index = finder.first + row;
+
+            let (item, positions) = match finder.matches.get(index) {
+                Some((_, item, positions)) => (&finder.items[*item], positions),
+                None => {
+                    frame.put(2, y, &" ".repeat(list_width), &normal);
+                    continue;
+                },
+            };
+
+            let style = if index == finder.selected { &selected } else { &normal };
+            let highlight = Style { bold: true, underline: true, ..style.clone() };
+
+            let mut x = frame.put(2, y, " ", style);
+            for (position, character) in item.label.chars().enumerate().take(list_width.saturating_sub(2)) {
+                x = frame.put(x, y, &character.to_string(), if positions.contains(&position) { &highlight } else { style });
+            }
+
+            frame.put(x, y, &" ".repeat((2 + list_width).saturating_sub(x)), style);
+        }
+
+        if list_width == width {
+            return;
+        }
+
+        // preview, centered on the line the item leads to
+        let item = finder.selected();
+        let current = item.map(|item| item.filename == self.filename).unwrap_or(false);
+
+        let preview = match (&finder.preview, current) {
+            (Some((filename, _, lines)), false) if item.map(|item| item.filename == *filename).unwrap_or(false) => &lines[..],
+            _ => &[],
+        };
+
+        let target = item.map(|item| item.cursor.y);
+        let start = target.map(|y| y.saturating_sub(height / 2)).unwrap_or(0);
+
+        let x = 2 + list_width + 1;
+        let preview_width = width - list_width - 1;
+        let default = self.theme.style("default");
+
+        for row in 0..height + 1 {
+            let y = row + 1;
+            let line = start + row;
+            let text = if current {
+                self.buffer.get(line).map(|line| line.iter().take(preview_width.saturating_sub(1)).collect::<String>())
+            } else {
+                preview.get(line).map(|line| line.chars().take(preview_width.saturating_sub(1)).collect::<String>())
+            };
+            let text = text.unwrap_or_default();
+            let style = if target == Some(line) && finder.source == Source::Symbols { &selected } else { &default };
+
+            frame.put(x - 1, y, " ", &normal);
+            frame.put(x, y, &format!(" {:<width$}", text, width = preview_width.saturating_sub(1)), style);
+        }
+    }
+}
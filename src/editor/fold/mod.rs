@@ -0,0 +1,246 @@
+use crate::editor::{Editor, Mode, Visual};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fold {
+    pub start: usize,
+    pub end: usize,
+    pub closed: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Folds {
+    folds: Vec<Fold>,
+}
+
+impl Folds {
+    pub fn new() -> Folds {
+        Folds {
+            folds: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, start: usize, end: usize) {
+        if start < end && !self.folds.iter().any(|fold| fold.start == start && fold.end == end) {
+            self.folds.push(Fold {
+                start,
+                end,
+                closed: true,
+            });
+        }
+    }
+
+    // computed folds replace the old ones, folds that start on the same line stay open or closed
+    pub fn replace(&mut self, ranges: Vec<(usize, usize)>) {
+        let old = std::mem::take(&mut self.folds);
+
+        for (start, end) in ranges.into_iter().filter(|(start, end)| start < end) {
+            let closed = old.iter().find(|fold| fold.start == start).map(|fold| fold.closed).unwrap_or(false);
+
+            self.folds.push(Fold {
+                start,
+                end,
+                closed,
+            });
+        }
+    }
+
+    // the outermost closed fold around the line, it is drawn as a single line
+    pub fn closed_at(&self, line: usize) -> Option<Fold> {
+        self.folds.iter()
+            .filter(|fold| fold.closed && fold.start <= line && line <= fold.end)
+            .min_by_key(|fold| (fold.start, std::cmp::Reverse(fold.end)))
+            .copied()
+    }
+
+    pub fn visible_start(&self, line: usize) -> usize {
+        self.closed_at(line).map(|fold| fold.start).unwrap_or(line)
+    }
+
+    pub fn next_line(&self, line: usize) -> usize {
+        self.closed_at(line).map(|fold| fold.end).unwrap_or(line) + 1
+    }
+
+    pub fn previous_line(&self, line: usize) -> usize {
+        self.visible_start(line.saturating_sub(1))
+    }
+
+    // the number of screen lines between two lines
+    pub fn rows(&self, from: usize, to: usize) -> usize {
+        let mut line = from;
+        let mut rows = 0;
+
+        while line < to {
+            line = self.next_line(line);
+
+            if line <= to {
+                rows += 1;
+            }
+        }
+
+        rows
+    }
+
+    pub fn line_at(&self, from: usize, rows: usize) -> usize {
+        (0..rows).fold(from, |line, _| self.next_line(line))
+    }
+
+    pub fn open(&mut self, line: usize) -> bool {
+        match self.closed_at(line) {
+            Some(closed) => {
+                self.folds.iter_mut().filter(|fold| **fold == closed).for_each(|fold| fold.closed = false);
+                true
+            },
+            None => false,
+        }
+    }
+
+    // opens every fold around the line, used when a search jumps into a fold
+    pub fn reveal(&mut self, line: usize) {
+        while self.open(line) {}
+    }
+
+    pub fn close(&mut self, line: usize) -> bool {
+        let fold = self.folds.iter_mut()
+            .filter(|fold| !fold.closed && fold.start <= line && line <= fold.end)
+            .min_by_key(|fold| fold.end - fold.start);
+
+        match fold {
+            Some(fold) => {
+                fold.closed = true;
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn toggle(&mut self, line: usize) -> bool {
+        self.open(line) || self.close(line)
+    }
+
+    pub fn set_all(&mut self, closed: bool) {
+        for fold in &mut self.folds {
+            fold.closed = closed;
+        }
+    }
+
+    // `count` lines were inserted before `at`
+    pub fn insert_lines(&mut self, at: usize, count: usize) {
+        for fold in &mut self.folds {
+            if fold.start >= at {
+                fold.start += count;
+                fold.end += count;
+            } else if fold.end >= at {
+                fold.end += count;
+            }
+        }
+    }
+
+    // the lines `at..at + count` were removed
+    pub fn remove_lines(&mut self, at: usize, count: usize) {
+        let removed = at + count;
+
+        for fold in &mut self.folds {
+            if fold.start >= removed {
+                fold.start -= count;
+                fold.end -= count;
+                continue;
+            }
+
+            if fold.start > at {
+                fold.start = at;
+            }
+
+            if fold.end >= removed {
+                fold.end -= count;
+            } else if fold.end >= at {
+                fold.end = at.saturating_sub(1);
+            }
+        }
+
+        self.folds.retain(|fold| fold.start < fold.end);
+    }
+}
+
+fn indent(line: &[char]) -> Option<usize> {
+    line.iter().position(|character| !character.is_whitespace())
+}
+
+// a fold starts on every line that is followed by a more indented one and ends before the indentation drops back
+pub fn indent_ranges(buffer: &[Vec<char>]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    let mut open: Vec<(usize, usize)> = Vec::new();
+    let mut last = 0;
+
+    for (index, line) in buffer.iter().enumerate() {
+        let level = match indent(line) {
+            Some(level) => level,
+            None => continue,
+        };
+
+        while open.last().map(|(_, open_level)| level <= *open_level).unwrap_or(false) {
+            let (start, _) = open.pop().unwrap_or_default();
+            ranges.push((start, last));
+        }
+
+        if let Some(previous) = buffer.get(last).and_then(|line| indent(line)) {
+            if level > previous && open.last().map(|(start, _)| *start != last).unwrap_or(true) {
+                open.push((last, previous));
+            }
+        }
+
+        last = index;
+    }
+
+    for (start, _) in open {
+        ranges.push((start, last));
+    }
+
+    ranges
+}
+
+impl Editor {
+    // indent and syntax folds follow the text, manual folds only move with it
+    pub fn update_folds(&mut self) {
+        match self.options.get_str("foldmethod").as_str() {
+            "indent" => self.folds.replace(indent_ranges(&self.buffer)),
+            "syntax" => self.folds.replace(self.syntax.fold_ranges(&self.buffer)),
+            _ => {},
+        }
+    }
+
+    pub(super) fn fold_command(&mut self, sequence: &str, visual: &Visual) -> Result<(), Box<dyn std::error::Error>> {
+        let found = match sequence {
+            "zf" if self.mode == Mode::Visual => {
+                if self.options.get_str("foldmethod") != "manual" {
+                    return Err("Cannot create a fold with the current foldmethod".into());
+                }
+
+                let (start, end) = (visual.y.min(self.cursor.y), visual.y.max(self.cursor.y));
+                self.folds.add(start, end);
+
+                self.cursor.y = start;
+                self.mode = Mode::Normal;
+                true
+            },
+            "zo" => self.folds.open(self.cursor.y),
+            "zc" => self.folds.close(self.cursor.y),
+            "za" => self.folds.toggle(self.cursor.y),
+            "zR" => {
+                self.folds.set_all(false);
+                true
+            },
+            "zM" => {
+                self.folds.set_all(true);
+                true
+            },
+            _ => true,
+        };
+
+        if !found {
+            return Err("No fold found".into());
+        }
+
+        self.refresh = true;
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+use crate::editor::{Editor, Cursor};
+use crate::editor::buffermanager::BufferManager;
+use crate::editor::list::List;
+
+use ignore::{WalkBuilder, WalkState};
+use regex::Regex;
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+// files with a null byte in their first block are binary
+const BINARY_CHECK: usize = 8192;
+
+type Match = (String, Cursor, String);
+
+pub fn display(path: &Path) -> String {
+    let path = path.strip_prefix("./").unwrap_or(path);
+    path.to_string_lossy().to_string()
+}
+
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_CHECK)].contains(&0)
+}
+
+// the first match of every line, the column is counted in characters
+fn search_lines<'a>(filename: &str, lines: impl Iterator<Item = &'a str>, regex: &Regex) -> Vec<Match> {
+    lines.enumerate()
+        .filter_map(|(y, line)| {
+            let found = regex.find(line)?;
+            let x = line[..found.start()].chars().count();
+
+            Some((filename.to_string(), Cursor { x, y }, line.trim().to_string()))
+        })
+        .collect()
+}
+
+fn search_file(path: &Path, regex: &Regex) -> Vec<Match> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(_) => return Vec::new(),
+    };
+
+    if is_binary(&bytes) {
+        return Vec::new();
+    }
+
+    let text = String::from_utf8_lossy(&bytes);
+    search_lines(&display(path), text.lines(), regex)
+}
+
+impl Editor {
+    // searches every file under the path that git does not ignore, open buffers are searched as they are
+    pub fn grep(&mut self, pattern: &str, root: &str, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        let regex = Regex::new(pattern)?;
+
+        let mut kept: HashMap<PathBuf, Vec<String>> = HashMap::new();
+        let text = |lines: &[Vec<char>]| lines.iter().map(|line| line.iter().collect()).collect();
+
+        if let (None, Ok(path)) = (&self.list, fs::canonicalize(&self.filename)) {
+            kept.insert(path, text(&self.buffer));
+        }
+
+        for (_, buffer) in manager.buffers.iter().enumerate().filter(|(index, _)| *index != manager.current) {
+            if let (Some(lines), Ok(path)) = (buffer.kept(), fs::canonicalize(&buffer.filename)) {
+                kept.insert(path, text(lines));
+            }
+        }
+
+        let paths = kept.keys().cloned().collect::<HashSet<PathBuf>>();
+        let (sender, receiver) = mpsc::channel::<(PathBuf, Vec<Match>)>();
+
+        WalkBuilder::new(root).build_parallel().run(|| {
+            let sender = sender.clone();
+            let regex = regex.clone();
+            let paths = paths.clone();
+
+            Box::new(move |entry| {
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(_) => return WalkState::Continue,
+                };
+
+                if !entry.file_type().map(|kind| kind.is_file()).unwrap_or(false) {
+                    return WalkState::Continue;
+                }
+
+                let path = entry.path();
+
+                // the matches of the buffers come from memory
+                let matches = if !paths.is_empty() && fs::canonicalize(path).map(|path| paths.contains(&path)).unwrap_or(false) {
+                    Vec::new()
+                } else {
+                    search_file(path, &regex)
+                };
+
+                if sender.send((path.to_path_buf(), matches)).is_err() {
+                    return WalkState::Quit;
+                }
+
+                WalkState::Continue
+            })
+        });
+
+        drop(sender);
+
+        let mut matches = Vec::new();
+
+        for (path, found) in receiver {
+            let lines = if kept.is_empty() { None } else { fs::canonicalize(&path).ok().and_then(|path| kept.get(&path)) };
+
+            match lines {
+                Some(lines) => matches.extend(search_lines(&display(&path), lines.iter().map(|line| line.as_str()), &regex)),
+                None => matches.extend(found),
+            }
+        }
+
+        matches.sort_by(|a, b| (&a.0, a.1.y).cmp(&(&b.0, b.1.y)));
+
+        if matches.is_empty() {
+            return Err(format!("No matches for `{}`", pattern).into());
+        }
+
+        let count = matches.len();
+        let lines = matches.iter()
+            .map(|(filename, cursor, text)| format!("{}:{}:{}: {}", filename, cursor.y + 1, cursor.x + 1, text))
+            .collect();
+
+        let locations = matches.into_iter()
+            .map(|(filename, cursor, _)| (filename, cursor))
+            .collect();
+
+        manager.open_list(self, "[Grep]", List::new(lines, locations))?;
+        self.log(&format!("{} matches", count));
+        Ok(())
+    }
+}
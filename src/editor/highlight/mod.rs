@@ -1,38 +1,44 @@
 use serde_json::Value;
-use console::Style;
 use lib_lexin::{Lexer, Token, Section};
+use regex::Regex;
 
+use crate::editor::{Change, config_path};
+
+#[cfg(feature = "tree-sitter")]
+use crate::editor::treesitter::Tree;
+
+use std::collections::HashMap;
 use std::path::Path;
 use std::ffi::OsStr;
 use std::fs;
-use std::env;
-
-#[derive(Clone, Debug)]
-pub struct Colors {
-    keywords: Style,
-    types: Style,
-    operators: Style,
-    integers: Style,
-    strings: Style,
 
-    pub default: Style,
+// decimal, hex, octal and binary literals with underscores, floats with exponents and type suffixes
+const NUMBERS: &str = r"\b(?:0x[0-9a-fA-F_]+|0o[0-7_]+|0b[01_]+|[0-9][0-9_]*(?:\.[0-9][0-9_]*)?(?:[eE][+-]?[0-9_]+)?)(?:[iu](?:8|16|32|64|128|size)|f32|f64)?\b";
 
-    pub bar: Style,
-    pub mode: Style,
-    pub line_numbers: Style,
-
-    pub background: u8,
+#[derive(Clone, Debug)]
+struct Matcher {
+    group: String,
+    regex: Regex,
+    priority: i64,
 }
 
 #[derive(Clone, Debug)]
 pub struct Syntax {
-    keywords: Vec<String>,
-    types: Vec<String>,
-    operators: Vec<String>,
-
+    matchers: Vec<Matcher>,
+
+    pub fold_pairs: Vec<(String, String)>,
+    pub pairs: Vec<(String, String)>,
+    pub autopairs: Vec<(char, char)>,
+    pub indent_increase: Regex,
+    pub indent_decrease: Regex,
+    pub line_comment: Option<String>,
+    pub block_comment: Option<(String, String)>,
     lexer: Lexer,
 
-    pub colors: Colors,
+    #[cfg(feature = "tree-sitter")]
+    tree: Option<Tree>,
+
+    pub links: HashMap<String, String>,
     pub filetype: String,
 }
 
@@ -46,7 +52,7 @@ fn remove_str_sym(string: String) -> String {
 impl Syntax {
     fn value_to_section(name: &str, value_array: &Value) -> Result<Section, Box<dyn std::error::Error>> {
         let array = value_array.as_array().ok_or::<Box<dyn std::error::Error>>("failed to parse config".into())?;
-        return if array.len() == 2 {
+        if array.len() == 2 {
             Ok(Section::new(name, &remove_str_sym(array[0].to_string()), &remove_str_sym(array[1].to_string())))
         } else {
             Err("section need a start and end".into())
@@ -62,6 +68,16 @@ impl Syntax {
         vec
     }
 
+    fn value_to_pairs(value_array: &Value) -> Vec<(String, String)> {
+        value_array.as_array()
+            .unwrap_or(&Vec::new())
+            .iter()
+            .map(Self::value_to_vec)
+            .filter(|pair| pair.len() == 2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .collect()
+    }
+
     fn vec_to_symbols(vector: &Vec<String>) -> Vec<(char, String)> {
         let mut symbols: Vec<(char, String)> = Vec::new();
         for symbol in vector {
@@ -71,12 +87,64 @@ impl Syntax {
         symbols
     }
 
+    // words only match on their own, `in` does not match inside `int`
+    fn words_to_regex(words: &[String]) -> Result<Regex, Box<dyn std::error::Error>> {
+        let mut words = words.iter().filter(|word| !word.is_empty()).collect::<Vec<&String>>();
+        words.sort_by_key(|word| std::cmp::Reverse(word.len()));
+
+        let boundary = |character: Option<char>| if character.map(|character| character.is_alphanumeric() || character == '_').unwrap_or(false) { "\\b" } else { "" };
+
+        let alternatives = words.iter()
+            .map(|word| format!("{}{}{}", boundary(word.chars().next()), regex::escape(word), boundary(word.chars().last())))
+            .collect::<Vec<String>>();
+
+        Ok(Regex::new(&format!("(?:{})", alternatives.join("|")))?)
+    }
+
+    fn add_matcher(&mut self, group: &str, regex: Regex, priority: i64) {
+        self.matchers.push(Matcher {
+            group: group.to_string(),
+            regex,
+            priority,
+        });
+    }
+
+    fn load_groups(&mut self, groups: &Value) -> Result<(), Box<dyn std::error::Error>> {
+        for group in groups.as_array().unwrap_or(&Vec::new()) {
+            let name = group["name"].as_str().ok_or("group needs a name")?;
+
+            let regex = if let Some(pattern) = group["regex"].as_str() {
+                Regex::new(pattern).map_err(|err| format!("`{}`: {}", name, err))?
+            } else if group["words"].is_array() {
+                Self::words_to_regex(&Self::value_to_vec(&group["words"]))?
+            } else {
+                return Err(format!("`{}` needs either a regex or words", name).into());
+            };
+
+            self.add_matcher(name, regex, group["priority"].as_i64().unwrap_or(0));
+
+            if let Some(link) = group["link"].as_str() {
+                self.links.insert(name.to_string(), link.to_string());
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn new(filename: &str) -> Result<Syntax, Box<dyn std::error::Error>> {
         let mut syntax = Syntax {
-            keywords: Vec::new(),
-            types: Vec::new(),
-            operators: Vec::new(),
-
+            matchers: Vec::new(),
+            fold_pairs: vec![(String::from("{"), String::from("}"))],
+            pairs: vec![
+                (String::from("("), String::from(")")),
+                (String::from("["), String::from("]")),
+                (String::from("{"), String::from("}")),
+            ],
+            autopairs: vec![('(', ')'), ('[', ']'), ('{', '}'), ('"', '"'), ('\'', '\'')],
+            indent_increase: Regex::new("[{(\\[:]\\s*$")?,
+            indent_decrease: Regex::new("^\\s*[})\\]]")?,
+            line_comment: None,
+            block_comment: None,
             lexer: Lexer::new(
                 &[],
                 &[],
@@ -84,131 +152,353 @@ impl Syntax {
                 true,
             ),
 
-            colors: Colors {
-                keywords: Style::new(),
-                types: Style::new(),
-                operators: Style::new(),
-                integers: Style::new(),
-                strings: Style::new(),
-
-                default: Style::new(),
-
-                bar: Style::new(),
-                mode: Style::new(),
-                line_numbers: Style::new(),
+            #[cfg(feature = "tree-sitter")]
+            tree: None,
 
-                background: 0,
-            },
+            links: HashMap::new(),
             filetype: String::new(),
         };
 
         // LOAD SYNTAX
         let extension = Path::new(filename)
             .extension()
-            .unwrap_or(&OsStr::new(""))
+            .unwrap_or(OsStr::new(""))
             .to_str()
             .unwrap_or("");
 
-        let home = env::var("HOME")?;
+        // a bundled grammar goes first, the definition file is optional next to it
+        #[cfg(feature = "tree-sitter")]
+        syntax.load_tree(extension);
+
         match extension {
             "rs" => {
-                let config = fs::read_to_string(home.clone() + "/.config/te/rust.json")?;
-                let json = serde_json::from_str::<Value>(&config)?;
-
-                // keywords
-                let keywords = Self::value_to_vec(&json["keywords"]);
-                syntax.lexer.keywords = keywords.clone();
-                syntax.keywords = keywords;
+                syntax.line_comment = Some(String::from("//"));
+                syntax.block_comment = Some((String::from("/*"), String::from("*/")));
 
-                // types
-                let types = Self::value_to_vec(&json["types"]);
-                syntax.lexer.keywords.extend(types.clone());
-                syntax.types = types;
+                let config = config_path("rust.json").and_then(|path| match fs::read_to_string(&path) {
+                    Ok(config) => Ok((path, config)),
+                    Err(err) => Err(format!("{}: {}", path, err).into()),
+                });
 
-                // types
-                syntax.lexer.symbols = Self::vec_to_symbols(&Self::value_to_vec(&json["symbols"]));
-                syntax.lexer.symbols.push((' ', String::new()));
-
-                // load string colors
-                syntax.lexer.sections.push(Self::value_to_section("string", &json["strings"])?);
-
-                syntax.operators = Self::value_to_vec(&json["operators"]);
+                match config {
+                    Ok((path, config)) => syntax.load_definition(&path, &config)?,
+                    Err(err) if !syntax.has_tree() => return Err(err),
+                    Err(_) => {},
+                }
 
                 syntax.filetype = String::from("rust");
             },
+            _ if syntax.has_tree() => {},
             _ => {
                 syntax.filetype = String::from("text");
             },
         }
 
-        // LOAD COLORS
-        let colors = fs::read_to_string(home + "/.config/te/colors.json")?;
-        let colors_json = serde_json::from_str::<Value>(&colors)?;
+        Ok(syntax)
+    }
+
+    #[cfg(feature = "tree-sitter")]
+    fn has_tree(&self) -> bool {
+        self.tree.is_some()
+    }
 
-        // include the colors
-        syntax.colors.background = colors_json["bg"].as_u64().unwrap_or(0) as u8;
+    #[cfg(not(feature = "tree-sitter"))]
+    fn has_tree(&self) -> bool {
+        false
+    }
 
-        // builtin
-        syntax.colors.keywords = Self::fg_color(&colors_json, "keywords").bold().on_color256(syntax.colors.background);
-        syntax.colors.types = Self::fg_color(&colors_json, "types").on_color256(syntax.colors.background);
-        syntax.colors.operators = Self::fg_color(&colors_json, "operators").on_color256(syntax.colors.background);
+    fn load_definition(&mut self, path: &str, config: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let json = serde_json::from_str::<Value>(config)?;
 
-        // literals
-        syntax.colors.integers = Self::fg_color(&colors_json, "integers").on_color256(syntax.colors.background);
-        syntax.colors.strings = Self::fg_color(&colors_json, "strings").on_color256(syntax.colors.background);
+        // keywords
+        let keywords = Self::value_to_vec(&json["keywords"]);
+        self.lexer.keywords = keywords.clone();
+        self.add_matcher("keywords", Self::words_to_regex(&keywords)?, 0);
 
-        // default color
-        syntax.colors.default = Style::new().on_color256(syntax.colors.background);
+        // types
+        let types = Self::value_to_vec(&json["types"]);
+        self.lexer.keywords.extend(types.clone());
+        self.add_matcher("types", Self::words_to_regex(&types)?, 0);
 
-        // line numbers
-        syntax.colors.line_numbers = Self::fg_color(&colors_json, "line_numbers").on_color256(syntax.colors.background);
+        // symbols
+        self.lexer.symbols = Self::vec_to_symbols(&Self::value_to_vec(&json["symbols"]));
+        self.lexer.symbols.push((' ', String::new()));
 
-        // bar color
-        syntax.colors.bar = Self::bg_color(&colors_json, "bar");
+        // load string colors
+        let strings = Self::value_to_section("string", &json["strings"])?;
+        self.add_matcher("strings", Regex::new(&format!("{}(?:\\\\.|.)*?{}", regex::escape(&strings.start), regex::escape(&strings.end)))?, 0);
+        self.lexer.sections.push(strings);
 
-        // mode colors
-        let mode_bg = colors_json["mode_bg"].as_u64().unwrap_or(0) as u8;
-        let mode_fg = colors_json["mode_fg"].as_u64().unwrap_or(0) as u8;
-        syntax.colors.mode = Style::new().color256(mode_fg).on_color256(mode_bg).bold();
+        self.add_matcher("operators", Self::words_to_regex(&Self::value_to_vec(&json["operators"]))?, 0);
+        self.add_matcher("integers", Regex::new(NUMBERS)?, 0);
 
-        Ok(syntax)
+        // groups declared by the language
+        self.load_groups(&json["groups"]).map_err(|err| format!("{}: {}", path, err))?;
+
+        // pairs that start and end a fold
+        if json["folds"].is_array() {
+            self.fold_pairs = Self::value_to_pairs(&json["folds"]);
+        }
+
+        // pairs that `%` jumps between on top of the brackets
+        self.pairs.extend(Self::value_to_pairs(&json["pairs"]));
+
+        // pairs that are closed while typing, only single characters can be typed through
+        if json["autopairs"].is_array() {
+            self.autopairs = Self::value_to_pairs(&json["autopairs"])
+                .into_iter()
+                .filter_map(|(open, close)| {
+                    let (mut open, mut close) = (open.chars(), close.chars());
+
+                    match (open.next(), open.next(), close.next(), close.next()) {
+                        (Some(open), None, Some(close), None) => Some((open, close)),
+                        _ => None,
+                    }
+                })
+                .collect();
+        }
+
+        // lines after an increasing line are indented once more, decreasing lines once less
+        if let Some(increase) = json["indent"]["increase"].as_str() {
+            self.indent_increase = Regex::new(increase).map_err(|err| format!("{}: {}", path, err))?;
+        }
+        if let Some(decrease) = json["indent"]["decrease"].as_str() {
+            self.indent_decrease = Regex::new(decrease).map_err(|err| format!("{}: {}", path, err))?;
+        }
+
+        // comment leaders, without a "comment" object rust comments are used
+        let comment = &json["comment"];
+        if comment.is_object() {
+            self.line_comment = comment["line"].as_str().map(String::from);
+
+            let block = Self::value_to_vec(&comment["block"]);
+            self.block_comment = if block.len() == 2 { Some((block[0].clone(), block[1].clone())) } else { None };
+        }
+
+        Ok(())
     }
 
-    fn fg_color(colors: &Value, name: &str) -> Style {
-        Style::new().color256(colors[name].as_u64().unwrap_or(0) as u8)
+    // captures like `function.method` fall back to `function`, the top level ones to the lexer groups
+    #[cfg(feature = "tree-sitter")]
+    fn load_tree(&mut self, extension: &str) {
+        let tree = match Tree::new(extension) {
+            Some(tree) => tree,
+            None => return,
+        };
+
+        for name in tree.capture_names() {
+            let link = match name.rsplit_once('.') {
+                Some((parent, _)) => parent,
+                None => match *name {
+                    "keyword" => "keywords",
+                    "type" => "types",
+                    "string" => "strings",
+                    "number" => "integers",
+                    "operator" => "operators",
+                    "comment" => "comments",
+                    _ => continue,
+                },
+            };
+
+            self.links.entry(name.to_string()).or_insert_with(|| link.to_string());
+        }
+
+        self.filetype = tree.filetype.to_string();
+        self.tree = Some(tree);
     }
 
-    fn bg_color(colors: &Value, name: &str) -> Style {
-        Style::new().on_color256(colors[name].as_u64().unwrap_or(0) as u8)
+    #[cfg(feature = "tree-sitter")]
+    pub fn update(&mut self, changes: &[Change]) {
+        if let Some(tree) = &mut self.tree {
+            tree.update(changes);
+        }
     }
 
-    fn colorize(&self, token: Token) -> String {
-        let token_str = token.as_string();
-        return if let Ok(content) = token.is_section("string") {
-            self.colors.strings.apply_to(format!("\"{}\"", content)).to_string()
-        } else if self.keywords.contains(&token_str) {
-            format!("{}", self.colors.keywords.apply_to(token_str))
-        } else if self.types.contains(&token_str) {
-            format!("{}", self.colors.types.apply_to(token_str))
-        } else if self.operators.contains(&token_str) {
-            format!("{}", self.colors.operators.apply_to(token_str))
-        } else if token.as_string().parse::<usize>().is_ok() {
-            format!("{}", self.colors.integers.apply_to(token_str))
-        } else {
-            format!("{}", self.colors.default.apply_to(token_str))
-        };
+    #[cfg(not(feature = "tree-sitter"))]
+    pub fn update(&mut self, _changes: &[Change]) {}
+
+    // a fold for every pair that spans lines, pairs in strings and comments are skipped
+    pub fn fold_ranges(&self, buffer: &[Vec<char>]) -> Vec<(usize, usize)> {
+        #[cfg(feature = "tree-sitter")]
+        if let Some(tree) = &self.tree {
+            return tree.fold_ranges();
+        }
+
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        let mut open: Vec<Vec<usize>> = vec![Vec::new(); self.fold_pairs.len()];
+
+        for (index, line) in buffer.iter().enumerate() {
+            for (_, _, pair, opening) in self.find_pairs(index, line, &self.fold_pairs) {
+                if opening {
+                    open[pair].push(index);
+                } else if let Some(start) = open[pair].pop() {
+                    ranges.push((start, index));
+                }
+            }
+        }
+
+        ranges
     }
 
-    pub fn highlight(&mut self, buffer: String) -> Result<String, Box<dyn std::error::Error>> {
-        let mut output = String::new();
+    pub fn find_pairs(&self, index: usize, line: &[char], pairs: &[(String, String)]) -> Vec<(usize, usize, usize, bool)> {
+        Self::find_pairs_in(&self.highlight(index, &line.iter().collect::<String>()), line, pairs)
+    }
 
-        self.lexer.load_str(&(buffer + "  "));
+    // the halves of `pairs` on a highlighted line as (column, length, pair, opening), strings and comments are skipped
+    pub fn find_pairs_in<G: AsRef<str>>(spans: &[(G, String)], line: &[char], pairs: &[(String, String)]) -> Vec<(usize, usize, usize, bool)> {
+        let mut found: Vec<(usize, usize, usize, bool)> = Vec::new();
+        let mut x = 0;
 
-        for token in self.lexer.tokenize()? {
-            output = output + &self.colorize(token);
+        let word = |character: Option<&char>| character.map(|character| character.is_alphanumeric() || *character == '_').unwrap_or(false);
+
+        for (group, text) in spans {
+            let group = group.as_ref();
+            let length = text.chars().count();
+
+            if group.contains("string") || group.contains("comment") {
+                x += length;
+                continue;
+            }
+
+            for column in x..x + length {
+                let candidates = pairs.iter().enumerate().flat_map(|(pair, (open, close))| [(pair, open, true), (pair, close, false)]);
+
+                for (pair, half, opening) in candidates {
+                    let half = half.chars().collect::<Vec<char>>();
+                    let end = column + half.len();
+
+                    if half.is_empty() || end > line.len() || line[column..end] != half[..] {
+                        continue;
+                    }
+
+                    // words like `begin` only count on their own
+                    if word(half.first()) && (column > 0 && word(line.get(column - 1)) || word(line.get(end))) {
+                        continue;
+                    }
+
+                    found.push((column, half.len(), pair, opening));
+                    break;
+                }
+            }
+
+            x += length;
+        }
+
+        found
+    }
+
+    pub fn keywords(&self) -> &[String] {
+        &self.lexer.keywords
+    }
+
+    // whether the characters on both sides of column `x` belong to the same string
+    pub fn in_string(&self, index: usize, line: &[char], x: usize) -> bool {
+        let mut start = 0;
+
+        for (group, text) in self.highlight(index, &line.iter().collect::<String>()) {
+            let end = start + text.chars().count();
+
+            if start < x && x < end {
+                return group.contains("string");
+            }
+
+            start = end;
+        }
+
+        false
+    }
+
+    #[cfg(feature = "tree-sitter")]
+    pub fn node_at(&self, y: usize, x: usize) -> Option<String> {
+        let node = self.tree.as_ref()?.node_at(y, x)?;
+        Some(format!("{} {}:{}-{}:{}", node.kind, node.start.1 + 1, node.start.0 + 1, node.end.1 + 1, node.end.0 + 1))
+    }
+
+    #[cfg(not(feature = "tree-sitter"))]
+    pub fn node_at(&self, _y: usize, _x: usize) -> Option<String> {
+        None
+    }
+
+    // structural motions go to (x, y) of a node start
+    #[cfg(feature = "tree-sitter")]
+    pub fn sibling_node(&self, y: usize, x: usize, forward: bool) -> Option<(usize, usize)> {
+        self.tree.as_ref()?.sibling(y, x, forward)
+    }
+
+    #[cfg(not(feature = "tree-sitter"))]
+    pub fn sibling_node(&self, _y: usize, _x: usize, _forward: bool) -> Option<(usize, usize)> {
+        None
+    }
+
+    #[cfg(feature = "tree-sitter")]
+    pub fn parent_node(&self, y: usize, x: usize) -> Option<(usize, usize)> {
+        self.tree.as_ref()?.parent(y, x)
+    }
+
+    #[cfg(not(feature = "tree-sitter"))]
+    pub fn parent_node(&self, _y: usize, _x: usize) -> Option<(usize, usize)> {
+        None
+    }
+
+    fn next_match(&self, matcher: &Matcher, line: &str, position: usize) -> Option<(usize, usize)> {
+        let mut start = position;
+
+        // empty matches are skipped so a bad pattern can not stall the highlighter
+        while start <= line.len() {
+            let found = matcher.regex.find_at(line, start)?;
+
+            if !found.is_empty() {
+                return Some((found.start(), found.end()));
+            }
+
+            start = found.start() + line[found.start()..].chars().next().map(|character| character.len_utf8()).unwrap_or(1);
         }
 
-        Ok(output)
+        None
+    }
+
+    // the leftmost match wins, on a tie the higher priority and then the longer match is used
+    #[cfg_attr(not(feature = "tree-sitter"), allow(unused_variables))]
+    pub fn highlight(&self, index: usize, line: &str) -> Vec<(&str, String)> {
+        // the tree knows more than the matchers, they are only used when there is no grammar
+        #[cfg(feature = "tree-sitter")]
+        if let Some(spans) = self.tree.as_ref().and_then(|tree| tree.highlight(index, line)) {
+            return spans;
+        }
+
+        let mut output: Vec<(&str, String)> = Vec::new();
+        let mut found = self.matchers.iter().map(|matcher| self.next_match(matcher, line, 0)).collect::<Vec<Option<(usize, usize)>>>();
+        let mut position = 0;
+
+        while position < line.len() {
+            for (index, matcher) in self.matchers.iter().enumerate() {
+                if found[index].map(|(start, _)| start < position).unwrap_or(false) {
+                    found[index] = self.next_match(matcher, line, position);
+                }
+            }
+
+            let best = found.iter()
+                .enumerate()
+                .filter_map(|(index, found)| found.map(|(start, end)| (index, start, end)))
+                .min_by_key(|(index, start, end)| (*start, -self.matchers[*index].priority, std::cmp::Reverse(*end), *index));
+
+            match best {
+                Some((index, start, end)) => {
+                    if start > position {
+                        output.push(("default", line[position..start].to_string()));
+                    }
+
+                    output.push((&self.matchers[index].group, line[start..end].to_string()));
+                    position = end;
+                },
+                None => {
+                    output.push(("default", line[position..].to_string()));
+                    position = line.len();
+                },
+            }
+        }
+
+        output
     }
 
     fn token_length(&mut self, token: &Token) -> usize {
@@ -252,4 +542,32 @@ impl Syntax {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
 
+    fn numbers(line: &str) -> Vec<String> {
+        let mut syntax = Syntax::new("test.txt").unwrap();
+        syntax.load_definition("test.json", r#"{"keywords": ["let"], "strings": ["\"", "\""], "operators": ["+", "=", ".."]}"#).unwrap();
+
+        syntax.highlight(0, line).into_iter()
+            .filter(|(group, _)| *group == "integers")
+            .map(|(_, text)| text)
+            .collect()
+    }
+
+    #[test]
+    fn number_literals() {
+        assert_eq!(numbers("let a = 0xFF + 0o17 + 0b1010_0101;"), ["0xFF", "0o17", "0b1010_0101"]);
+        assert_eq!(numbers("1_000 + 1.5 + 2.5e-3 + 1e10"), ["1_000", "1.5", "2.5e-3", "1e10"]);
+        assert_eq!(numbers("2u32 + 3i64 + 4usize + 1.5f32"), ["2u32", "3i64", "4usize", "1.5f32"]);
+    }
+
+    #[test]
+    fn numbers_inside_other_tokens() {
+        // ranges, fields, method calls and names that end in digits
+        assert_eq!(numbers("0..10"), ["0", "10"]);
+        assert_eq!(numbers("x1 + point.0 + 1.max(2)"), ["0", "1", "2"]);
+        assert_eq!(numbers("u32 + 7abc"), Vec::<String>::new());
+    }
+}
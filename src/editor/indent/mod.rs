@@ -0,0 +1,111 @@
+use crate::editor::{Editor, Mode, Visual};
+
+fn leading(line: &[char]) -> usize {
+    line.iter().take_while(|character| character.is_whitespace()).count()
+}
+
+fn is_blank(line: &[char]) -> bool {
+    line.iter().all(|character| character.is_whitespace())
+}
+
+impl Editor {
+    // a shiftwidth of 0 follows the tabsize
+    pub fn shiftwidth(&self) -> usize {
+        match self.options.get_int("shiftwidth") {
+            0 => self.options.get_int("tabsize"),
+            width => width,
+        }
+    }
+
+    // the indentation line `y` gets from the closest non blank line above it
+    pub fn indentation(&self, y: usize) -> usize {
+        let shiftwidth = self.shiftwidth();
+
+        let mut indentation = match self.buffer[..y].iter().rev().find(|line| !is_blank(line)) {
+            Some(line) if self.syntax.indent_increase.is_match(&line.iter().collect::<String>()) => leading(line) + shiftwidth,
+            Some(line) => leading(line),
+            None => 0,
+        };
+
+        if self.syntax.indent_decrease.is_match(&self.buffer[y].iter().collect::<String>()) {
+            indentation = indentation.saturating_sub(shiftwidth);
+        }
+
+        indentation
+    }
+
+    // replaces the leading whitespace of line `y`, the cursor stays on the same character
+    pub fn set_indentation(&mut self, y: usize, indentation: usize) {
+        let old = leading(&self.buffer[y]);
+        self.buffer[y].splice(0..old, std::iter::repeat_n(' ', indentation));
+        self.changed(y, 1, 1);
+
+        if self.cursor.y == y {
+            self.cursor.x = if self.cursor.x >= old { self.cursor.x - old + indentation } else { indentation };
+            self.clamp = self.cursor.x;
+        }
+
+        self.refresh = true;
+    }
+
+    fn reindent(&mut self, start: usize, end: usize) {
+        for y in start..=end {
+            let indentation = if is_blank(&self.buffer[y]) { 0 } else { self.indentation(y) };
+            self.set_indentation(y, indentation);
+        }
+    }
+
+    fn shift(&mut self, start: usize, end: usize, right: bool) {
+        let shiftwidth = self.shiftwidth();
+
+        for y in start..=end {
+            if is_blank(&self.buffer[y]) {
+                continue;
+            }
+
+            let indentation = leading(&self.buffer[y]);
+            self.set_indentation(y, if right { indentation + shiftwidth } else { indentation.saturating_sub(shiftwidth) });
+        }
+    }
+
+    // a closing bracket typed as the first character of a line moves the line back
+    pub fn indent_closing(&mut self) {
+        let line = &self.buffer[self.cursor.y];
+
+        if self.cursor.x == 0 || !is_blank(&line[..self.cursor.x - 1]) {
+            return;
+        }
+
+        if self.syntax.indent_decrease.is_match(&line.iter().collect::<String>()) {
+            let indentation = self.indentation(self.cursor.y);
+            self.set_indentation(self.cursor.y, indentation);
+        }
+    }
+
+    // `==`, `>>` and `<<` work on the cursor line, `=`, `>` and `<` on the lines of the selection
+    pub(super) fn indent_command(&mut self, command: &str, visual: &Visual) {
+        let (start, end) = if self.mode == Mode::Visual {
+            (visual.y.min(self.cursor.y), visual.y.max(self.cursor.y))
+        } else {
+            (self.cursor.y, self.cursor.y)
+        };
+
+        self.indent_lines(command, start, end);
+        self.mode = Mode::Normal;
+    }
+
+    // the cursor goes to the first of the lines, also used by the ex commands `:>` and `:<`
+    pub fn indent_lines(&mut self, command: &str, start: usize, end: usize) {
+        match command {
+            "==" | "=" => self.reindent(start, end),
+            ">>" | ">" => self.shift(start, end, true),
+            "<<" | "<" => self.shift(start, end, false),
+            _ => return,
+        }
+
+        self.cursor.y = start;
+        self.cursor.x = leading(&self.buffer[start]).min(self.buffer[start].len().saturating_sub(1));
+        self.clamp = self.cursor.x;
+        self.refresh = true;
+    }
+}
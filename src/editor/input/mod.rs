@@ -0,0 +1,629 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Sender, Receiver, RecvTimeoutError};
+use std::io;
+use std::time::{Duration, Instant};
+use std::thread;
+
+const ESC: u8 = 0x1b;
+const PASTE_START: &[u8] = b"\x1b[200~";
+const PASTE_END: &[u8] = b"\x1b[201~";
+
+// a terminal can drop the end marker, the paste is given up after this many bytes or this long
+const MAX_PASTE: usize = 1 << 20;
+const PASTE_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Escape,
+    Backspace,
+    Tab,
+    ArrowLeft,
+    ArrowRight,
+    ArrowUp,
+    ArrowDown,
+    Home,
+    End,
+    Insert,
+    Del,
+    PageUp,
+    PageDown,
+    F(u8),
+    Unknown,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub meta: bool,
+}
+
+impl Modifiers {
+    pub const NONE: Modifiers = Modifiers { shift: false, ctrl: false, alt: false, meta: false };
+    pub const SHIFT: Modifiers = Modifiers { shift: true, ctrl: false, alt: false, meta: false };
+    pub const CTRL: Modifiers = Modifiers { shift: false, ctrl: true, alt: false, meta: false };
+    pub const ALT: Modifiers = Modifiers { shift: false, ctrl: false, alt: true, meta: false };
+
+    // xterm and kitty both encode modifiers as `1 + bitmask`
+    fn from_parameter(parameter: u32) -> Modifiers {
+        let mask = parameter.saturating_sub(1);
+
+        Modifiers {
+            shift: mask & 1 != 0,
+            alt: mask & 2 != 0,
+            ctrl: mask & 4 != 0,
+            meta: mask & (8 | 32) != 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyEvent {
+    pub key: Key,
+    pub modifiers: Modifiers,
+}
+
+impl KeyEvent {
+    pub fn new(key: Key) -> KeyEvent {
+        KeyEvent {
+            key,
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    fn with(key: Key, modifiers: Modifiers) -> KeyEvent {
+        KeyEvent {
+            key,
+            modifiers,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MouseKind {
+    Press(MouseButton),
+    Drag(MouseButton),
+    Release(MouseButton),
+    ScrollUp,
+    ScrollDown,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MouseEvent {
+    pub kind: MouseKind,
+    pub x: usize,
+    pub y: usize,
+    pub modifiers: Modifiers,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    Key(KeyEvent),
+    Mouse(MouseEvent),
+    Paste(String),
+    // another thread has something for the editor
+    Wake,
+}
+
+fn control(byte: u8) -> KeyEvent {
+    match byte {
+        b'\r' | b'\n' => KeyEvent::new(Key::Enter),
+        b'\t' => KeyEvent::new(Key::Tab),
+        0x7f | 0x08 => KeyEvent::new(Key::Backspace),
+        ESC => KeyEvent::new(Key::Escape),
+        0x00 => KeyEvent::with(Key::Char(' '), Modifiers::CTRL),
+        0x01..=0x1a => KeyEvent::with(Key::Char((b'a' + byte - 1) as char), Modifiers::CTRL),
+        _ => KeyEvent::with(Key::Char((b'\\' + byte - 0x1c) as char), Modifiers::CTRL),
+    }
+}
+
+fn utf8_length(byte: u8) -> usize {
+    match byte {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    }
+}
+
+// a single key that does not start with escape, returns the event and the amount of bytes used
+fn parse_plain(bytes: &[u8], complete: bool) -> Option<(KeyEvent, usize)> {
+    let byte = *bytes.first()?;
+
+    if byte < 0x20 || byte == 0x7f {
+        return Some((control(byte), 1));
+    }
+
+    let length = utf8_length(byte);
+    if bytes.len() < length {
+        return if complete { Some((KeyEvent::new(Key::Unknown), bytes.len())) } else { None };
+    }
+
+    let key = std::str::from_utf8(&bytes[..length])
+        .ok()
+        .and_then(|string| string.chars().next())
+        .map(Key::Char)
+        .unwrap_or(Key::Unknown);
+
+    Some((KeyEvent::new(key), length))
+}
+
+fn parameters(bytes: &[u8]) -> Vec<u32> {
+    // sub-parameters separated by `:` are dropped, only the first one is used
+    String::from_utf8_lossy(bytes)
+        .split(';')
+        .map(|parameter| parameter.split(':').next().unwrap_or("").parse::<u32>().unwrap_or(1))
+        .collect()
+}
+
+fn kitty_key(codepoint: u32) -> Key {
+    match codepoint {
+        13 => Key::Enter,
+        27 => Key::Escape,
+        9 => Key::Tab,
+        127 | 8 => Key::Backspace,
+        57399..=57408 => Key::Char((b'0' + (codepoint - 57399) as u8) as char),
+        57414 => Key::Enter,
+        _ => char::from_u32(codepoint).map(Key::Char).unwrap_or(Key::Unknown),
+    }
+}
+
+fn tilde_key(number: u32) -> Key {
+    match number {
+        1 | 7 => Key::Home,
+        2 => Key::Insert,
+        3 => Key::Del,
+        4 | 8 => Key::End,
+        5 => Key::PageUp,
+        6 => Key::PageDown,
+        11..=15 => Key::F((number - 10) as u8),
+        17..=21 => Key::F((number - 11) as u8),
+        23..=26 => Key::F((number - 12) as u8),
+        28 | 29 => Key::F((number - 13) as u8),
+        31..=34 => Key::F((number - 14) as u8),
+        _ => Key::Unknown,
+    }
+}
+
+fn final_key(byte: u8) -> Key {
+    match byte {
+        b'A' => Key::ArrowUp,
+        b'B' => Key::ArrowDown,
+        b'C' => Key::ArrowRight,
+        b'D' => Key::ArrowLeft,
+        b'H' => Key::Home,
+        b'F' => Key::End,
+        b'P' => Key::F(1),
+        b'Q' => Key::F(2),
+        b'R' => Key::F(3),
+        b'S' => Key::F(4),
+        _ => Key::Unknown,
+    }
+}
+
+// SGR mouse reports look like `CSI < button ; column ; row M`, `m` instead of `M` is a release
+fn parse_mouse(body: &[u8], terminator: u8) -> Event {
+    let parameters = parameters(&body[1..]);
+
+    if parameters.len() < 3 {
+        return Event::Key(KeyEvent::new(Key::Unknown));
+    }
+
+    let code = parameters[0];
+    let button = match code & 3 {
+        0 => MouseButton::Left,
+        1 => MouseButton::Middle,
+        _ => MouseButton::Right,
+    };
+
+    let kind = if code & 64 != 0 {
+        if code & 1 == 0 { MouseKind::ScrollUp } else { MouseKind::ScrollDown }
+    } else if terminator == b'm' {
+        MouseKind::Release(button)
+    } else if code & 32 != 0 {
+        MouseKind::Drag(button)
+    } else {
+        MouseKind::Press(button)
+    };
+
+    Event::Mouse(MouseEvent {
+        kind,
+        x: parameters[1].saturating_sub(1) as usize,
+        y: parameters[2].saturating_sub(1) as usize,
+        modifiers: Modifiers {
+            shift: code & 4 != 0,
+            alt: code & 8 != 0,
+            ctrl: code & 16 != 0,
+            meta: false,
+        },
+    })
+}
+
+fn parse_csi(body: &[u8], terminator: u8) -> KeyEvent {
+    let parameters = parameters(body);
+    let modifiers = Modifiers::from_parameter(parameters.get(1).copied().unwrap_or(1));
+
+    match terminator {
+        b'~' => KeyEvent::with(tilde_key(parameters[0]), modifiers),
+        b'u' => {
+            let mut event = KeyEvent::with(kitty_key(parameters[0]), modifiers);
+
+            // shifted letters are reported as their lowercase code point
+            if let Key::Char(character) = event.key {
+                if modifiers.shift && character.is_ascii_lowercase() && !modifiers.ctrl {
+                    event.key = Key::Char(character.to_ascii_uppercase());
+                    event.modifiers.shift = false;
+                }
+            }
+
+            event
+        },
+        b'Z' => KeyEvent::with(Key::Tab, Modifiers { shift: true, ..modifiers }),
+        _ => KeyEvent::with(final_key(terminator), modifiers),
+    }
+}
+
+// parses the next event from the start of `bytes`, `complete` means that no more bytes are coming
+// so sequences that were cut off have to be resolved now
+pub fn parse(bytes: &[u8], complete: bool) -> Option<(Event, usize)> {
+    if bytes.first() != Some(&ESC) {
+        return parse_plain(bytes, complete).map(|(event, length)| (Event::Key(event), length));
+    }
+
+    if bytes.starts_with(PASTE_START) {
+        // a paste is finished by the end marker, without it every key after it would be pasted
+        return match bytes.windows(PASTE_END.len()).position(|window| window == PASTE_END) {
+            Some(end) => Some((paste(&bytes[..end]), end + PASTE_END.len())),
+            None if bytes.len() >= PASTE_START.len() + MAX_PASTE => Some((paste(&bytes[..PASTE_START.len() + MAX_PASTE]), PASTE_START.len() + MAX_PASTE)),
+            None => None,
+        };
+    }
+
+    if bytes.starts_with(b"\x1b[<") {
+        let end = bytes[2..].iter().position(|byte| !(0x20..=0x3f).contains(byte)).map(|position| position + 2);
+
+        if let Some(end) = end.filter(|end| bytes[*end] == b'M' || bytes[*end] == b'm') {
+            return Some((parse_mouse(&bytes[2..end], bytes[end]), end + 1));
+        }
+    }
+
+    parse_escape(bytes, complete).map(|(event, length)| (Event::Key(event), length))
+}
+
+// the text after the start marker
+fn paste(bytes: &[u8]) -> Event {
+    Event::Paste(String::from_utf8_lossy(&bytes[PASTE_START.len()..]).to_string())
+}
+
+fn parse_escape(bytes: &[u8], complete: bool) -> Option<(KeyEvent, usize)> {
+    let alt = Modifiers::ALT;
+
+    match bytes.get(1) {
+        // a lone escape is only the escape key once no sequence followed it in time
+        None if complete => Some((KeyEvent::new(Key::Escape), 1)),
+        None => None,
+        Some(b'[') => {
+            // CSI: parameter and intermediate bytes followed by a final byte
+            let end = bytes[2..].iter().position(|byte| !(0x20..=0x3f).contains(byte)).map(|position| position + 2);
+
+            match end {
+                Some(end) if (0x40..=0x7e).contains(&bytes[end]) => Some((parse_csi(&bytes[2..end], bytes[end]), end + 1)),
+                None if !complete => None,
+                _ => Some((KeyEvent::with(Key::Char('['), alt), 2)),
+            }
+        },
+        Some(b'O') => {
+            // SS3: application cursor keys and F1-F4, anything else was escape and `O` typed quickly
+            match bytes.get(2) {
+                Some(b'M') => Some((KeyEvent::new(Key::Enter), 3)),
+                Some(byte) if final_key(*byte) != Key::Unknown => Some((KeyEvent::new(final_key(*byte)), 3)),
+                Some(_) => Some((KeyEvent::with(Key::Char('O'), alt), 2)),
+                None if complete => Some((KeyEvent::with(Key::Char('O'), alt), 2)),
+                None => None,
+            }
+        },
+        Some(&ESC) => Some((KeyEvent::new(Key::Escape), 1)),
+        Some(_) => {
+            let (mut event, length) = parse_plain(&bytes[1..], complete)?;
+            event.modifiers.alt = true;
+
+            Some((event, length + 1))
+        },
+    }
+}
+
+fn read_bytes(fd: i32, buffer: &mut [u8]) -> io::Result<usize> {
+    let length = unsafe { libc::read(fd, buffer.as_mut_ptr() as *mut libc::c_void, buffer.len()) };
+
+    if length < 0 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(length as usize)
+    }
+}
+
+pub struct Input {
+    sender: Sender<Vec<u8>>,
+    receiver: Receiver<Vec<u8>>,
+    bytes: VecDeque<u8>,
+    // when the paste that is still waiting for its end marker started
+    paste: Option<Instant>,
+}
+
+impl Input {
+    pub fn new() -> Input {
+        let (sender, receiver) = mpsc::channel();
+        let reader = sender.clone();
+
+        thread::spawn(move || {
+            let mut buffer = [0u8; 4096];
+
+            while let Ok(length) = read_bytes(libc::STDIN_FILENO, &mut buffer) {
+                if length == 0 || reader.send(buffer[..length].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Input {
+            sender,
+            receiver,
+            bytes: VecDeque::new(),
+            paste: None,
+        }
+    }
+
+    // sending an empty chunk wakes up a `read` that waits for keys
+    pub fn waker(&self) -> Sender<Vec<u8>> {
+        self.sender.clone()
+    }
+
+    fn receive(&mut self, timeout: Option<Duration>) -> Result<bool, Box<dyn std::error::Error>> {
+        let bytes = match timeout {
+            Some(timeout) => match self.receiver.recv_timeout(timeout) {
+                Ok(bytes) => bytes,
+                Err(RecvTimeoutError::Timeout) => return Ok(false),
+                Err(err) => return Err(err.into()),
+            },
+            None => self.receiver.recv()?,
+        };
+
+        self.bytes.extend(bytes);
+        Ok(true)
+    }
+
+    // waits at most `timeout` for a key, `escape` is how long an unfinished escape sequence may take
+    pub fn read(&mut self, timeout: Option<Duration>, escape: Duration) -> Result<Option<Event>, Box<dyn std::error::Error>> {
+        loop {
+            let bytes = self.bytes.make_contiguous();
+
+            if let Some((event, length)) = parse(bytes, false) {
+                self.paste = None;
+                self.bytes.drain(..length);
+                return Ok(Some(event));
+            }
+
+            if bytes.starts_with(PASTE_START) && self.paste.get_or_insert_with(Instant::now).elapsed() >= PASTE_TIMEOUT {
+                let event = paste(bytes);
+
+                self.paste = None;
+                self.bytes.clear();
+                return Ok(Some(event));
+            }
+
+            if self.bytes.is_empty() {
+                if !self.receive(timeout)? {
+                    return Ok(None);
+                }
+
+                if self.bytes.is_empty() {
+                    return Ok(Some(Event::Wake));
+                }
+            } else if !self.receive(Some(escape))? {
+                let bytes = self.bytes.make_contiguous();
+
+                if let Some((event, length)) = parse(bytes, true) {
+                    self.bytes.drain(..length);
+                    return Ok(Some(event));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: Key) -> Option<Event> {
+        Some(Event::Key(KeyEvent::new(key)))
+    }
+
+    fn with(key: Key, modifiers: Modifiers) -> Option<Event> {
+        Some(Event::Key(KeyEvent::with(key, modifiers)))
+    }
+
+    fn mouse(kind: MouseKind, x: usize, y: usize, modifiers: Modifiers) -> Option<Event> {
+        Some(Event::Mouse(MouseEvent { kind, x, y, modifiers }))
+    }
+
+    // the bytes, whether more of them could still arrive, the event and how many bytes it used
+    fn check(cases: &[(&[u8], bool, Option<Event>, usize)]) {
+        for (bytes, complete, expected, length) in cases {
+            let expected = expected.clone().map(|event| (event, *length));
+            assert_eq!(parse(bytes, *complete), expected, "{:?} complete: {}", String::from_utf8_lossy(bytes), complete);
+        }
+    }
+
+    // every event of a stream that is known to be complete
+    fn events(mut bytes: &[u8]) -> Vec<Event> {
+        let mut events = Vec::new();
+
+        while let Some((event, length)) = parse(bytes, true) {
+            events.push(event);
+            bytes = &bytes[length..];
+        }
+
+        events
+    }
+
+    const SHIFT: Modifiers = Modifiers::SHIFT;
+    const CTRL: Modifiers = Modifiers::CTRL;
+    const ALT: Modifiers = Modifiers::ALT;
+
+    #[test]
+    fn plain_keys() {
+        check(&[
+            (b"a", false, key(Key::Char('a')), 1),
+            (b"\r", false, key(Key::Enter), 1),
+            (b"\t", false, key(Key::Tab), 1),
+            (b"\x7f", false, key(Key::Backspace), 1),
+            (b"\x01", false, with(Key::Char('a'), CTRL), 1),
+            (b"\x00", false, with(Key::Char(' '), CTRL), 1),
+            (b"\x1c", false, with(Key::Char('\\'), CTRL), 1),
+            ("é".as_bytes(), false, key(Key::Char('é')), 2),
+            ("€x".as_bytes(), false, key(Key::Char('€')), 3),
+        ]);
+    }
+
+    #[test]
+    fn csi_keys() {
+        check(&[
+            (b"\x1b[A", false, key(Key::ArrowUp), 3),
+            (b"\x1b[D", false, key(Key::ArrowLeft), 3),
+            (b"\x1b[H", false, key(Key::Home), 3),
+            (b"\x1b[F", false, key(Key::End), 3),
+            (b"\x1b[1;5C", false, with(Key::ArrowRight, CTRL), 6),
+            (b"\x1b[1;2D", false, with(Key::ArrowLeft, SHIFT), 6),
+            (b"\x1b[1;3A", false, with(Key::ArrowUp, ALT), 6),
+            (b"\x1b[1;9B", false, with(Key::ArrowDown, Modifiers { meta: true, ..Modifiers::NONE }), 6),
+            (b"\x1b[2~", false, key(Key::Insert), 4),
+            (b"\x1b[3~", false, key(Key::Del), 4),
+            (b"\x1b[5;5~", false, with(Key::PageUp, CTRL), 6),
+            (b"\x1b[6~", false, key(Key::PageDown), 4),
+            (b"\x1b[1~", false, key(Key::Home), 4),
+            (b"\x1b[4~", false, key(Key::End), 4),
+            (b"\x1b[15~", false, key(Key::F(5)), 5),
+            (b"\x1b[24~", false, key(Key::F(12)), 5),
+            (b"\x1b[Z", false, with(Key::Tab, SHIFT), 3),
+            (b"\x1b[Aj", false, key(Key::ArrowUp), 3),
+        ]);
+    }
+
+    #[test]
+    fn ss3_keys() {
+        check(&[
+            (b"\x1bOA", false, key(Key::ArrowUp), 3),
+            (b"\x1bOH", false, key(Key::Home), 3),
+            (b"\x1bOP", false, key(Key::F(1)), 3),
+            (b"\x1bOS", false, key(Key::F(4)), 3),
+            (b"\x1bOM", false, key(Key::Enter), 3),
+            // escape, `O` and another key typed quickly
+            (b"\x1bOj", false, with(Key::Char('O'), ALT), 2),
+        ]);
+    }
+
+    #[test]
+    fn kitty_keys() {
+        check(&[
+            (b"\x1b[97u", false, key(Key::Char('a')), 5),
+            (b"\x1b[97;5u", false, with(Key::Char('a'), CTRL), 7),
+            (b"\x1b[97;2u", false, key(Key::Char('A')), 7),
+            (b"\x1b[97:65;2u", false, key(Key::Char('A')), 10),
+            (b"\x1b[97;6u", false, with(Key::Char('a'), Modifiers { shift: true, ctrl: true, ..Modifiers::NONE }), 7),
+            (b"\x1b[27u", false, key(Key::Escape), 5),
+            (b"\x1b[13;2u", false, with(Key::Enter, SHIFT), 7),
+            (b"\x1b[127u", false, key(Key::Backspace), 6),
+            (b"\x1b[57400u", false, key(Key::Char('1')), 8),
+        ]);
+    }
+
+    #[test]
+    fn sgr_mouse() {
+        check(&[
+            (b"\x1b[<0;10;5M", false, mouse(MouseKind::Press(MouseButton::Left), 9, 4, Modifiers::NONE), 10),
+            (b"\x1b[<0;10;5m", false, mouse(MouseKind::Release(MouseButton::Left), 9, 4, Modifiers::NONE), 10),
+            (b"\x1b[<2;1;1M", false, mouse(MouseKind::Press(MouseButton::Right), 0, 0, Modifiers::NONE), 9),
+            (b"\x1b[<32;3;4M", false, mouse(MouseKind::Drag(MouseButton::Left), 2, 3, Modifiers::NONE), 10),
+            (b"\x1b[<64;1;1M", false, mouse(MouseKind::ScrollUp, 0, 0, Modifiers::NONE), 10),
+            (b"\x1b[<65;1;1M", false, mouse(MouseKind::ScrollDown, 0, 0, Modifiers::NONE), 10),
+            (b"\x1b[<16;120;40M", false, mouse(MouseKind::Press(MouseButton::Left), 119, 39, CTRL), 13),
+        ]);
+    }
+
+    #[test]
+    fn bracketed_paste() {
+        check(&[
+            (b"\x1b[200~hi\x1b[201~", false, Some(Event::Paste(String::from("hi"))), 14),
+            (b"\x1b[200~a\x1bb\rc\x1b[201~x", false, Some(Event::Paste(String::from("a\x1bb\rc"))), 17),
+            // a paste waits for its end marker even when nothing else is coming
+            (b"\x1b[200~hi", false, None, 0),
+            (b"\x1b[200~hi", true, None, 0),
+        ]);
+    }
+
+    #[test]
+    fn paste_without_end_marker() {
+        let mut bytes = PASTE_START.to_vec();
+        bytes.resize(PASTE_START.len() + MAX_PASTE - 1, b'a');
+        assert_eq!(parse(&bytes, false), None);
+
+        // the keys after it are parsed again
+        bytes.extend(b"a\x1b[A");
+        let length = PASTE_START.len() + MAX_PASTE;
+
+        assert_eq!(parse(&bytes, false), Some((Event::Paste("a".repeat(MAX_PASTE)), length)));
+        assert_eq!(parse(&bytes[length..], false), key(Key::ArrowUp).map(|event| (event, 3)));
+    }
+
+    #[test]
+    fn split_reads() {
+        check(&[
+            (b"\x1b[", false, None, 0),
+            (b"\x1b[1;5", false, None, 0),
+            (b"\x1b[<0;10", false, None, 0),
+            (b"\x1bO", false, None, 0),
+            (b"\xc3", false, None, 0),
+            (b"\xc3", true, key(Key::Unknown), 1),
+            (b"\x1b[", true, with(Key::Char('['), ALT), 2),
+            (b"\x1bO", true, with(Key::Char('O'), ALT), 2),
+        ]);
+    }
+
+    #[test]
+    fn lone_escape() {
+        check(&[
+            (b"\x1b", false, None, 0),
+            (b"\x1b", true, key(Key::Escape), 1),
+            (b"\x1b\x1b", false, key(Key::Escape), 1),
+            // the editor splits an alt chord without a mapping into escape and the key
+            (b"\x1bj", false, with(Key::Char('j'), ALT), 2),
+        ]);
+    }
+
+    #[test]
+    fn streams() {
+        assert_eq!(events(b"a\x1b[B\x1b[<0;1;1Mb\x1b"), vec![
+            Event::Key(KeyEvent::new(Key::Char('a'))),
+            Event::Key(KeyEvent::new(Key::ArrowDown)),
+            Event::Mouse(MouseEvent { kind: MouseKind::Press(MouseButton::Left), x: 0, y: 0, modifiers: Modifiers::NONE }),
+            Event::Key(KeyEvent::new(Key::Char('b'))),
+            Event::Key(KeyEvent::new(Key::Escape)),
+        ]);
+
+        assert_eq!(events(b"\x1b[200~x\x1b[201~\x1b[1;5D"), vec![
+            Event::Paste(String::from("x")),
+            Event::Key(KeyEvent::with(Key::ArrowLeft, CTRL)),
+        ]);
+    }
+}
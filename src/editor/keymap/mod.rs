@@ -0,0 +1,325 @@
+use crate::editor::Mode;
+use crate::editor::input::{Key, KeyEvent, Modifiers};
+
+const MODES: [Mode; 3] = [Mode::Normal, Mode::Insert, Mode::Visual];
+
+// commands of more than one key, a visual selection indents with a single key instead
+const SEQUENCES: [(&str, &[Mode]); 17] = [
+    ("zf", &[Mode::Normal, Mode::Visual]),
+    ("zo", &[Mode::Normal, Mode::Visual]),
+    ("zc", &[Mode::Normal, Mode::Visual]),
+    ("za", &[Mode::Normal, Mode::Visual]),
+    ("zR", &[Mode::Normal, Mode::Visual]),
+    ("zM", &[Mode::Normal, Mode::Visual]),
+    ("==", &[Mode::Normal]),
+    (">>", &[Mode::Normal]),
+    ("<<", &[Mode::Normal]),
+    ("gc", &[Mode::Normal, Mode::Visual]),
+    ("gd", &[Mode::Normal, Mode::Visual]),
+    ("gr", &[Mode::Normal, Mode::Visual]),
+    ("]d", &[Mode::Normal, Mode::Visual]),
+    ("[d", &[Mode::Normal, Mode::Visual]),
+    ("]n", &[Mode::Normal, Mode::Visual]),
+    ("[n", &[Mode::Normal, Mode::Visual]),
+    ("gp", &[Mode::Normal, Mode::Visual]),
+];
+
+#[derive(Clone, Debug)]
+pub enum Action {
+    Keys(Vec<KeyEvent>),
+    Command(String),
+    Sequence(&'static str),
+}
+
+#[derive(Clone, Debug)]
+pub struct Mapping {
+    mode: Mode,
+    lhs: Vec<KeyEvent>,
+    rhs: String,
+
+    pub action: Action,
+    pub noremap: bool,
+}
+
+pub struct Lookup<'a> {
+    pub exact: Option<&'a Mapping>,
+    pub prefix: bool,
+}
+
+fn key_from_name(name: &str) -> Option<Key> {
+    Some(match name.to_lowercase().as_str() {
+        "cr" | "enter" | "return" => Key::Enter,
+        "esc" => Key::Escape,
+        "tab" => Key::Tab,
+        "bs" => Key::Backspace,
+        "del" => Key::Del,
+        "space" => Key::Char(' '),
+        "lt" => Key::Char('<'),
+        "bar" => Key::Char('|'),
+        "left" => Key::ArrowLeft,
+        "right" => Key::ArrowRight,
+        "up" => Key::ArrowUp,
+        "down" => Key::ArrowDown,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "insert" => Key::Insert,
+        _ if name.len() > 1 && name.starts_with(['f', 'F']) => Key::F(name[1..].parse::<u8>().ok()?),
+        _ => {
+            let mut chars = name.chars();
+            let character = chars.next()?;
+            return if chars.next().is_none() { Some(Key::Char(character)) } else { None };
+        },
+    })
+}
+
+fn key_name(event: &KeyEvent) -> String {
+    let name = match &event.key {
+        Key::Enter => "CR".to_string(),
+        Key::Escape => "Esc".to_string(),
+        Key::Tab => "Tab".to_string(),
+        Key::Backspace => "BS".to_string(),
+        Key::Del => "Del".to_string(),
+        Key::Char(' ') => "Space".to_string(),
+        Key::Char('<') => "lt".to_string(),
+        Key::Char('|') => "Bar".to_string(),
+        Key::ArrowLeft => "Left".to_string(),
+        Key::ArrowRight => "Right".to_string(),
+        Key::ArrowUp => "Up".to_string(),
+        Key::ArrowDown => "Down".to_string(),
+        Key::Home => "Home".to_string(),
+        Key::End => "End".to_string(),
+        Key::PageUp => "PageUp".to_string(),
+        Key::PageDown => "PageDown".to_string(),
+        Key::Insert => "Insert".to_string(),
+        Key::F(number) => format!("F{}", number),
+        Key::Char(character) if event.modifiers == Modifiers::NONE => {
+            return character.to_string();
+        },
+        Key::Char(character) => character.to_string(),
+        key => format!("{:?}", key),
+    };
+
+    let mut prefix = String::new();
+    if event.modifiers.ctrl {
+        prefix += "C-";
+    }
+    if event.modifiers.shift {
+        prefix += "S-";
+    }
+    if event.modifiers.alt {
+        prefix += "A-";
+    }
+    if event.modifiers.meta {
+        prefix += "D-";
+    }
+
+    format!("<{}{}>", prefix, name)
+}
+
+pub fn parse_keys(notation: &str, leader: &str) -> Result<Vec<KeyEvent>, Box<dyn std::error::Error>> {
+    let mut keys: Vec<KeyEvent> = Vec::new();
+    let mut rest = notation;
+
+    while let Some(character) = rest.chars().next() {
+        let special = rest.strip_prefix('<')
+            .and_then(|inner| inner.split_once('>'))
+            .filter(|(name, _)| !name.is_empty() && !name.contains('<'));
+
+        match special {
+            Some((name, remaining)) => {
+                rest = remaining;
+
+                if name.eq_ignore_ascii_case("leader") {
+                    keys.extend(parse_keys(leader, "")?);
+                    continue;
+                } else if name.eq_ignore_ascii_case("nop") {
+                    continue;
+                }
+
+                let mut modifiers = Modifiers::NONE;
+                let mut name = name;
+
+                while let Some((modifier, remaining)) = name.split_once('-').filter(|(_, remaining)| !remaining.is_empty()) {
+                    match modifier.to_lowercase().as_str() {
+                        "c" => modifiers.ctrl = true,
+                        "s" => modifiers.shift = true,
+                        "a" | "m" => modifiers.alt = true,
+                        "d" => modifiers.meta = true,
+                        _ => return Err(format!("Unknown modifier: `{}`", modifier).into()),
+                    }
+                    name = remaining;
+                }
+
+                let mut key = key_from_name(name).ok_or_else(|| format!("Unknown key: `<{}>`", name))?;
+
+                // control characters do not have a case, <C-A> and <C-a> are the same key
+                if let Key::Char(character) = key {
+                    if modifiers.ctrl {
+                        key = Key::Char(character.to_ascii_lowercase());
+                    }
+                }
+
+                keys.push(KeyEvent {
+                    key,
+                    modifiers,
+                });
+            },
+            None => {
+                rest = &rest[character.len_utf8()..];
+                keys.push(KeyEvent::new(Key::Char(character)));
+            },
+        }
+    }
+
+    Ok(keys)
+}
+
+pub struct Keymap {
+    mappings: Vec<Mapping>,
+    // the sequences of the editor, mappings go over them and noremap does not skip them
+    builtins: Vec<Mapping>,
+}
+
+impl Keymap {
+    pub fn new() -> Keymap {
+        let builtins = SEQUENCES.iter()
+            .flat_map(|(sequence, modes)| modes.iter().map(move |mode| Mapping {
+                mode: *mode,
+                lhs: sequence.chars().map(|character| KeyEvent::new(Key::Char(character))).collect(),
+                rhs: sequence.to_string(),
+
+                action: Action::Sequence(sequence),
+                noremap: true,
+            }))
+            .collect();
+
+        Keymap {
+            mappings: Vec::new(),
+            builtins,
+        }
+    }
+
+    fn parse_command(name: &str) -> Option<(Vec<Mode>, bool, bool)> {
+        let name = name.strip_prefix(':').unwrap_or(name);
+
+        let (modes, rest) = match name.chars().next()? {
+            _ if matches!(name, "map" | "noremap" | "unmap") => (vec![Mode::Normal, Mode::Visual], name),
+            'n' => (vec![Mode::Normal], &name[1..]),
+            'i' => (vec![Mode::Insert], &name[1..]),
+            'v' => (vec![Mode::Visual], &name[1..]),
+            _ => return None,
+        };
+
+        match rest {
+            "map" => Some((modes, false, false)),
+            "noremap" => Some((modes, true, false)),
+            "unmap" => Some((modes, false, true)),
+            _ => None,
+        }
+    }
+
+    pub fn execute(&mut self, name: &str, arguments: &str, leader: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let (modes, noremap, unmap) = Self::parse_command(name).ok_or("not a mapping command")?;
+        let arguments = arguments.trim();
+
+        let (lhs, rhs) = match arguments.split_once(char::is_whitespace) {
+            Some((lhs, rhs)) => (lhs, rhs.trim()),
+            None => (arguments, ""),
+        };
+
+        if unmap {
+            let lhs = parse_keys(lhs, leader)?;
+            let count = self.mappings.len();
+
+            self.mappings.retain(|mapping| !(modes.contains(&mapping.mode) && mapping.lhs == lhs));
+
+            return if self.mappings.len() == count {
+                Err("No such mapping".into())
+            } else {
+                Ok(String::new())
+            };
+        }
+
+        if rhs.is_empty() {
+            // a bare `:map` lists the mappings of every mode
+            let modes = if lhs.is_empty() && name.trim_start_matches(':') == "map" { MODES.to_vec() } else { modes };
+            let lhs = parse_keys(lhs, leader)?;
+            return Ok(self.list(&modes, &lhs));
+        }
+
+        self.map(&modes, lhs, rhs, noremap, leader)?;
+        Ok(String::new())
+    }
+
+    pub fn map(&mut self, modes: &[Mode], lhs: &str, rhs: &str, noremap: bool, leader: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let lhs = parse_keys(lhs, leader)?;
+
+        if lhs.is_empty() {
+            return Err("Mapping needs at least one key".into());
+        }
+
+        // a right hand side starting with `:` runs as a command without passing through the command line
+        let action = match rhs.strip_prefix(':') {
+            Some(cmd) => {
+                let cmd = cmd.strip_suffix("<CR>").or_else(|| cmd.strip_suffix("<cr>")).unwrap_or(cmd);
+                Action::Command(format!(":{}", cmd))
+            },
+            None => Action::Keys(parse_keys(rhs, leader)?),
+        };
+
+        for mode in modes {
+            self.mappings.retain(|mapping| !(mapping.mode == *mode && mapping.lhs == lhs));
+            self.mappings.push(Mapping {
+                mode: *mode,
+                lhs: lhs.clone(),
+                rhs: rhs.to_string(),
+
+                action: action.clone(),
+                noremap,
+            });
+        }
+
+        Ok(())
+    }
+
+    // keys that are not remapped only look for the sequences of the editor
+    pub fn lookup(&self, mode: Mode, keys: &[KeyEvent], remap: bool) -> Lookup<'_> {
+        let mut lookup = Lookup {
+            exact: None,
+            prefix: false,
+        };
+
+        let mappings = self.mappings.iter().filter(|_| remap);
+
+        for mapping in self.builtins.iter().chain(mappings).filter(|mapping| mapping.mode == mode) {
+            if mapping.lhs == keys {
+                lookup.exact = Some(mapping);
+            } else if mapping.lhs.starts_with(keys) {
+                lookup.prefix = true;
+            }
+        }
+
+        lookup
+    }
+
+    fn list(&self, modes: &[Mode], prefix: &[KeyEvent]) -> String {
+        let mut output: Vec<String> = Vec::new();
+
+        for mode in MODES.iter().filter(|mode| modes.contains(mode)) {
+            for mapping in self.mappings.iter().filter(|mapping| mapping.mode == *mode && mapping.lhs.starts_with(prefix)) {
+                let mode = format!("{:?}", mapping.mode).to_lowercase().chars().next().unwrap_or(' ');
+                let lhs = mapping.lhs.iter().map(key_name).collect::<String>();
+
+                output.push(format!("{} {} {}{}", mode, lhs, if mapping.noremap { "*" } else { "" }, mapping.rhs));
+            }
+        }
+
+        if output.is_empty() {
+            String::from("No mapping found")
+        } else {
+            output.join(" | ")
+        }
+    }
+}
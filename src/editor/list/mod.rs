@@ -0,0 +1,81 @@
+use crate::editor::{Editor, Cursor, highlight};
+use crate::editor::buffermanager::BufferManager;
+use crate::editor::fold::Folds;
+
+// a buffer of lines that each lead somewhere, enter opens the location of the cursor line
+#[derive(Clone, Debug)]
+pub struct List {
+    lines: Vec<String>,
+    locations: Vec<(String, Cursor)>,
+    // the listing of a directory is read again instead of kept
+    directory: Option<String>,
+}
+
+impl List {
+    pub fn new(lines: Vec<String>, locations: Vec<(String, Cursor)>) -> List {
+        List {
+            lines,
+            locations,
+            directory: None,
+        }
+    }
+
+    pub fn directory(directory: &str, lines: Vec<String>, locations: Vec<(String, Cursor)>) -> List {
+        List {
+            lines,
+            locations,
+            directory: Some(directory.to_string()),
+        }
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.directory.as_deref()
+    }
+
+    pub fn location(&self, line: usize) -> Option<&(String, Cursor)> {
+        self.locations.get(line)
+    }
+}
+
+impl Editor {
+    // lists are not files, their lines are kept by the buffer manager
+    pub fn show_list(&mut self, name: &str, list: List) -> Result<(), Box<dyn std::error::Error>> {
+        self.flush_changes();
+        self.buffer = list.lines.iter().map(|line| line.chars().collect()).collect();
+
+        if self.buffer.is_empty() {
+            self.buffer.push(Vec::new());
+        }
+
+        self.filename = name.to_string();
+        self.syntax = highlight::Syntax::new(name)?;
+        self.folds = Folds::new();
+        self.list = Some(list);
+        self.replaced();
+
+        self.signs.open(name);
+        self.lsp.current = String::new();
+        Ok(())
+    }
+
+    // lists are only changed by what made them
+    pub fn modifiable(&mut self) -> bool {
+        if self.list.is_some() {
+            self.log(&format!("`{}` is read-only", self.filename));
+            return false;
+        }
+
+        true
+    }
+
+    pub fn open_location(&mut self, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        let location = self.list.as_ref().and_then(|list| list.location(self.cursor.y).cloned());
+
+        if let Some((filename, cursor)) = location {
+            manager.open(self, &filename)?;
+            self.goto(cursor);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1077 @@
+use crate::editor::{Editor, Cursor, Mode, Change, config_path};
+use crate::editor::buffermanager::BufferManager;
+use crate::editor::popup::{Popup, Pick};
+use crate::editor::signs::Sign;
+use crate::editor::list::List;
+use crate::editor::finder::Item;
+
+use serde_json::{json, Value};
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+// positions in the protocol count utf-16 code units, the buffer counts characters
+fn to_utf16(line: &[char], x: usize) -> usize {
+    line.iter().take(x).map(|character| character.len_utf16()).sum()
+}
+
+fn from_utf16(line: &[char], column: usize) -> usize {
+    let mut units = 0;
+
+    for (x, character) in line.iter().enumerate() {
+        if units >= column {
+            return x;
+        }
+        units += character.len_utf16();
+    }
+
+    line.len()
+}
+
+pub fn uri(filename: &str) -> String {
+    let path = fs::canonicalize(filename)
+        .or_else(|_| env::current_dir().map(|directory| directory.join(filename)))
+        .map(|path| path.to_string_lossy().to_string())
+        .unwrap_or_else(|_| filename.to_string());
+
+    let mut uri = String::from("file://");
+
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    uri
+}
+
+// the path of a file uri, relative to the working directory when it is inside of it
+pub fn path(uri: &str) -> Option<String> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::new();
+    let mut index = 0;
+
+    while index < encoded.len() {
+        let escaped = encoded.get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(byte) if encoded[index] == b'%' => {
+                bytes.push(byte);
+                index += 3;
+            },
+            _ => {
+                bytes.push(encoded[index]);
+                index += 1;
+            },
+        }
+    }
+
+    let path = String::from_utf8(bytes).ok()?;
+    let relative = env::current_dir().ok()
+        .and_then(|directory| Path::new(&path).strip_prefix(directory).ok().map(|path| path.to_string_lossy().to_string()));
+
+    Some(relative.unwrap_or(path))
+}
+
+fn position(value: &Value) -> (usize, usize) {
+    (value["line"].as_u64().unwrap_or(0) as usize, value["character"].as_u64().unwrap_or(0) as usize)
+}
+
+// the text of a hover, markup and marked strings are shown as they are
+fn hover_text(contents: &Value) -> String {
+    match contents {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts.iter().map(hover_text).collect::<Vec<String>>().join("\n\n"),
+        Value::Object(_) => contents["value"].as_str().unwrap_or("").to_string(),
+        _ => String::new(),
+    }
+}
+
+// locations come as a location, a list of them or a list of links
+fn locations(result: &Value) -> Vec<(String, (usize, usize))> {
+    let list = match result {
+        Value::Array(list) => list.clone(),
+        Value::Null => Vec::new(),
+        location => vec![location.clone()],
+    };
+
+    list.iter()
+        .filter_map(|location| {
+            let uri = location["uri"].as_str().or(location["targetUri"].as_str())?;
+            let range = if location["range"].is_object() { &location["range"] } else { &location["targetSelectionRange"] };
+
+            Some((path(uri)?, position(&range["start"])))
+        })
+        .collect()
+}
+
+// edits are applied from the last to the first so the positions of the others stay valid,
+// edits at the same place keep their order
+// the splices are returned as (start, removed, inserted) lines in the order they were made
+fn apply_edits(lines: &mut Vec<Vec<char>>, edits: &[Value]) -> Vec<(usize, usize, usize)> {
+    let mut edits = edits.iter()
+        .map(|edit| (position(&edit["range"]["start"]), position(&edit["range"]["end"]), edit["newText"].as_str().unwrap_or("")))
+        .collect::<Vec<((usize, usize), (usize, usize), &str)>>();
+
+    edits.reverse();
+    edits.sort_by_key(|(start, _, _)| std::cmp::Reverse(*start));
+
+    if lines.is_empty() {
+        lines.push(Vec::new());
+    }
+
+    let mut splices = Vec::new();
+
+    for (start, end, text) in edits {
+        let clamp = |(y, column): (usize, usize)| {
+            if y >= lines.len() {
+                (lines.len() - 1, lines[lines.len() - 1].len())
+            } else {
+                (y, from_utf16(&lines[y], column))
+            }
+        };
+
+        let (start, end) = (clamp(start), clamp(end));
+
+        let mut replacement = text.split('\n')
+            .map(|line| line.trim_end_matches('\r').chars().collect::<Vec<char>>())
+            .collect::<Vec<Vec<char>>>();
+
+        let tail = lines[end.0][end.1..].to_vec();
+        let last = replacement.len() - 1;
+
+        replacement[0].splice(0..0, lines[start.0][..start.1].iter().copied());
+        replacement[last].extend(tail);
+
+        splices.push((start.0, end.0 - start.0 + 1, replacement.len()));
+        lines.splice(start.0..=end.0, replacement);
+    }
+
+    splices
+}
+
+// the text a server gets, every line ends in a newline like in the file
+fn text(lines: &[Vec<char>]) -> String {
+    lines.iter().map(|line| line.iter().collect::<String>() + "\n").collect()
+}
+
+fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let mut body = vec![0; length?];
+    reader.read_exact(&mut body).ok()?;
+
+    serde_json::from_slice(&body).ok()
+}
+
+#[derive(Debug)]
+enum Request {
+    Initialize,
+    Hover,
+    Definition,
+    References,
+    Rename,
+    CodeAction,
+    Completion(Cursor),
+    Formatting(String, i64),
+    Symbols,
+}
+
+#[derive(Debug)]
+struct Document {
+    version: i64,
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+    pub severity: u64,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn parse(value: &Value) -> Diagnostic {
+        Diagnostic {
+            start: position(&value["range"]["start"]),
+            end: position(&value["range"]["end"]),
+            severity: value["severity"].as_u64().unwrap_or(1),
+            message: value["message"].as_str().unwrap_or("").to_string(),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self.severity {
+            1 => "error",
+            2 => "warning",
+            3 => "info",
+            _ => "hint",
+        }
+    }
+}
+
+struct Client {
+    command: String,
+    process: Child,
+    stdin: ChildStdin,
+
+    next_id: i64,
+    requests: HashMap<i64, Request>,
+
+    // nothing but the initialize request may be sent before the server answered it
+    initialized: bool,
+    queue: Vec<Value>,
+    capabilities: Value,
+
+    documents: HashMap<String, Document>,
+}
+
+impl Client {
+    fn start(name: &str, config: &Value, sender: Sender<(String, Value)>, waker: Sender<Vec<u8>>) -> Result<Client, Box<dyn std::error::Error>> {
+        let command = config["command"].as_str().ok_or(format!("No command for the {} language server", name))?;
+        let arguments = config["args"].as_array().map(|arguments| arguments.iter().filter_map(|argument| argument.as_str()).collect::<Vec<&str>>()).unwrap_or_default();
+
+        let mut process = Command::new(command)
+            .args(arguments)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| format!("Failed to start `{}`: {}", command, err))?;
+
+        let stdin = process.stdin.take().ok_or("No stdin")?;
+        let stdout = process.stdout.take().ok_or("No stdout")?;
+
+        // every message wakes up the editor, null means the server is gone
+        let server = name.to_string();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stdout);
+
+            loop {
+                let message = read_message(&mut reader).unwrap_or(Value::Null);
+                let gone = message.is_null();
+
+                if sender.send((server.clone(), message)).is_err() || waker.send(Vec::new()).is_err() || gone {
+                    break;
+                }
+            }
+        });
+
+        let mut client = Client {
+            command: command.to_string(),
+            process,
+            stdin,
+
+            next_id: 0,
+            requests: HashMap::new(),
+
+            initialized: false,
+            queue: Vec::new(),
+            capabilities: Value::Null,
+
+            documents: HashMap::new(),
+        };
+
+        let root = uri(".");
+        client.request("initialize", json!({
+            "processId": std::process::id(),
+            "rootUri": root,
+            "workspaceFolders": [{"uri": root, "name": name}],
+            "capabilities": {
+                "textDocument": {
+                    "synchronization": {"didSave": true},
+                    "hover": {"contentFormat": ["plaintext", "markdown"]},
+                    "completion": {"completionItem": {"snippetSupport": false}},
+                    "definition": {"linkSupport": true},
+                    "publishDiagnostics": {},
+                    "codeAction": {"codeActionLiteralSupport": {"codeActionKind": {"valueSet": ["", "quickfix", "refactor", "source"]}}},
+                    "rename": {},
+                    "formatting": {},
+                    "documentSymbol": {"hierarchicalDocumentSymbolSupport": true},
+                },
+                "workspace": {"applyEdit": true, "configuration": true, "workspaceEdit": {"documentChanges": true}},
+            },
+        }), Request::Initialize)?;
+
+        Ok(client)
+    }
+
+    fn write(&mut self, message: Value) -> Result<(), Box<dyn std::error::Error>> {
+        let body = message.to_string();
+
+        write!(self.stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+        self.stdin.flush()?;
+        Ok(())
+    }
+
+    fn send(&mut self, message: Value) -> Result<(), Box<dyn std::error::Error>> {
+        if self.initialized {
+            self.write(message)
+        } else {
+            self.queue.push(message);
+            Ok(())
+        }
+    }
+
+    fn request(&mut self, method: &str, params: Value, request: Request) -> Result<(), Box<dyn std::error::Error>> {
+        self.next_id += 1;
+        self.requests.insert(self.next_id, request);
+
+        let message = json!({"jsonrpc": "2.0", "id": self.next_id, "method": method, "params": params});
+
+        if method == "initialize" {
+            self.write(message)
+        } else {
+            self.send(message)
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> Result<(), Box<dyn std::error::Error>> {
+        self.send(json!({"jsonrpc": "2.0", "method": method, "params": params}))
+    }
+
+    fn reply(&mut self, id: &Value, result: Value) -> Result<(), Box<dyn std::error::Error>> {
+        self.write(json!({"jsonrpc": "2.0", "id": id, "result": result}))
+    }
+
+    fn initialize(&mut self, capabilities: Value) -> Result<(), Box<dyn std::error::Error>> {
+        self.capabilities = capabilities;
+        self.initialized = true;
+
+        self.write(json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}))?;
+
+        for message in std::mem::take(&mut self.queue) {
+            self.write(message)?;
+        }
+
+        Ok(())
+    }
+
+    // 0 is no syncing, 1 the whole text and 2 only the changed part
+    fn sync_kind(&self) -> u64 {
+        let sync = &self.capabilities["textDocumentSync"];
+        sync.as_u64().or(sync["change"].as_u64()).unwrap_or(2)
+    }
+
+    fn open(&mut self, uri: &str, language: &str, lines: &[Vec<char>]) -> Result<(), Box<dyn std::error::Error>> {
+        self.documents.insert(uri.to_string(), Document {
+            version: 0,
+        });
+
+        self.notify("textDocument/didOpen", json!({
+            "textDocument": {"uri": uri, "languageId": language, "version": 0, "text": text(lines)},
+        }))
+    }
+
+    // every line ends in a newline, so the edits of the editor are ranges of whole lines
+    fn change(&mut self, uri: &str, changes: &[Change], lines: &[Vec<char>]) -> Result<(), Box<dyn std::error::Error>> {
+        let kind = self.sync_kind();
+        let document = match self.documents.get_mut(uri) {
+            Some(document) if !changes.is_empty() => document,
+            _ => return Ok(()),
+        };
+
+        document.version += 1;
+        let version = document.version;
+
+        let changes = if kind == 1 || changes.iter().any(|change| change.removed == usize::MAX) {
+            vec![json!({"text": text(lines)})]
+        } else {
+            changes.iter().map(|change| json!({
+                "range": {"start": {"line": change.start, "character": 0}, "end": {"line": change.start + change.removed, "character": 0}},
+                "text": change.lines.iter().map(|line| line.clone() + "\n").collect::<String>(),
+            })).collect()
+        };
+
+        if kind == 0 {
+            return Ok(());
+        }
+
+        self.notify("textDocument/didChange", json!({
+            "textDocument": {"uri": uri, "version": version},
+            "contentChanges": changes,
+        }))
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.write(json!({"jsonrpc": "2.0", "id": 0, "method": "shutdown"}));
+        let _ = self.write(json!({"jsonrpc": "2.0", "method": "exit"}));
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+pub struct Lsp {
+    config: Value,
+    clients: HashMap<String, Client>,
+    failed: HashSet<String>,
+
+    sender: Sender<(String, Value)>,
+    receiver: Receiver<(String, Value)>,
+    waker: Sender<Vec<u8>>,
+
+    // the uri of the current buffer
+    pub current: String,
+    pub diagnostics: HashMap<String, Vec<Diagnostic>>,
+}
+
+impl Lsp {
+    // servers are configured per filetype or extension in "servers.json"
+    pub fn new(waker: Sender<Vec<u8>>) -> Lsp {
+        let config = config_path("servers.json").ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|config| serde_json::from_str::<Value>(&config).ok())
+            .unwrap_or(Value::Null);
+
+        let (sender, receiver) = mpsc::channel();
+
+        Lsp {
+            config,
+            clients: HashMap::new(),
+            failed: HashSet::new(),
+
+            sender,
+            receiver,
+            waker,
+
+            current: String::new(),
+            diagnostics: HashMap::new(),
+        }
+    }
+
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        self.diagnostics.get(&self.current).map(|diagnostics| &diagnostics[..]).unwrap_or(&[])
+    }
+
+    // diagnostics move with the lines of the current buffer until the server sends new ones
+    pub fn insert_lines(&mut self, at: usize, count: usize) {
+        for diagnostic in self.diagnostics.get_mut(&self.current).into_iter().flatten() {
+            for position in [&mut diagnostic.start, &mut diagnostic.end] {
+                if position.0 >= at {
+                    position.0 += count;
+                }
+            }
+        }
+    }
+
+    // the lines `at..at + count` were removed, the diagnostics starting on them go with them
+    pub fn remove_lines(&mut self, at: usize, count: usize) {
+        let Some(diagnostics) = self.diagnostics.get_mut(&self.current) else {
+            return;
+        };
+
+        diagnostics.retain(|diagnostic| diagnostic.start.0 < at || diagnostic.start.0 >= at + count);
+        for diagnostic in diagnostics.iter_mut() {
+            for position in [&mut diagnostic.start, &mut diagnostic.end] {
+                if position.0 >= at + count {
+                    position.0 -= count;
+                } else if position.0 >= at {
+                    *position = (at, 0);
+                }
+            }
+        }
+    }
+}
+
+impl Editor {
+    fn extension(&self) -> String {
+        Path::new(&self.filename).extension().map(|extension| extension.to_string_lossy().to_string()).unwrap_or_default()
+    }
+
+    fn server_name(&self) -> Option<String> {
+        [self.syntax.filetype.clone(), self.extension()].into_iter()
+            .find(|name| self.lsp.config[name.as_str()].is_object())
+    }
+
+    fn language_id(&self) -> String {
+        if self.syntax.filetype == "text" { self.extension() } else { self.syntax.filetype.clone() }
+    }
+
+    // starts the server of the buffer, the document is opened with the next edits
+    pub fn lsp_attach(&mut self) {
+        self.lsp.current = uri(&self.filename);
+
+        let name = match self.server_name() {
+            Some(name) => name,
+            None => return,
+        };
+
+        if !self.lsp.clients.contains_key(&name) && !self.lsp.failed.contains(&name) {
+            match Client::start(&name, &self.lsp.config[name.as_str()], self.lsp.sender.clone(), self.lsp.waker.clone()) {
+                Ok(client) => {
+                    self.lsp.clients.insert(name, client);
+                },
+                Err(err) => {
+                    self.lsp.failed.insert(name);
+                    self.log(&err.to_string());
+                },
+            }
+        }
+    }
+
+    // sends the edits since the last time, a document the server does not know yet is opened instead
+    pub fn lsp_sync(&mut self, changes: &[Change]) {
+        if self.lsp.current.is_empty() {
+            return;
+        }
+
+        let (uri, language) = (self.lsp.current.clone(), self.language_id());
+        let result = match self.server_name().and_then(|name| self.lsp.clients.get_mut(&name)) {
+            Some(client) if client.documents.contains_key(&uri) => client.change(&uri, changes, &self.buffer),
+            Some(client) => client.open(&uri, &language, &self.buffer),
+            None => Ok(()),
+        };
+
+        if let Err(err) = result {
+            self.log(&err.to_string());
+        }
+    }
+
+    pub fn lsp_saved(&mut self) {
+        let uri = self.lsp.current.clone();
+
+        if let Some(client) = self.server_name().and_then(|name| self.lsp.clients.get_mut(&name)) {
+            let _ = client.notify("textDocument/didSave", json!({"textDocument": {"uri": uri}}));
+        }
+    }
+
+    fn client(&mut self) -> Result<&mut Client, Box<dyn std::error::Error>> {
+        self.flush_changes();
+
+        let name = self.server_name().ok_or(format!("No language server for {}", self.syntax.filetype))?;
+        self.lsp.clients.get_mut(&name).ok_or(format!("The {} language server is not running", name).into())
+    }
+
+    fn text_position(&self) -> Value {
+        json!({
+            "textDocument": {"uri": self.lsp.current},
+            "position": {"line": self.cursor.y, "character": to_utf16(&self.buffer[self.cursor.y], self.cursor.x)},
+        })
+    }
+
+    pub fn hover(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let params = self.text_position();
+        self.client()?.request("textDocument/hover", params, Request::Hover)
+    }
+
+    pub fn definition(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let params = self.text_position();
+        self.client()?.request("textDocument/definition", params, Request::Definition)
+    }
+
+    pub fn references(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let mut params = self.text_position();
+        params["context"] = json!({"includeDeclaration": true});
+
+        self.client()?.request("textDocument/references", params, Request::References)
+    }
+
+    pub fn rename(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if name.is_empty() {
+            return Err("Usage: :rename [NAME]".into());
+        }
+
+        let mut params = self.text_position();
+        params["newName"] = json!(name);
+
+        self.client()?.request("textDocument/rename", params, Request::Rename)
+    }
+
+    // the actions for the cursor line and its diagnostics
+    pub fn code_actions(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let y = self.cursor.y;
+        let diagnostics = self.lsp.diagnostics().iter()
+            .filter(|diagnostic| diagnostic.start.0 <= y && y <= diagnostic.end.0)
+            .map(|diagnostic| json!({
+                "range": {
+                    "start": {"line": diagnostic.start.0, "character": diagnostic.start.1},
+                    "end": {"line": diagnostic.end.0, "character": diagnostic.end.1},
+                },
+                "severity": diagnostic.severity,
+                "message": diagnostic.message,
+            }))
+            .collect::<Vec<Value>>();
+
+        let params = json!({
+            "textDocument": {"uri": self.lsp.current},
+            "range": {
+                "start": {"line": y, "character": 0},
+                "end": {"line": y, "character": to_utf16(&self.buffer[y], self.buffer[y].len())},
+            },
+            "context": {"diagnostics": diagnostics},
+        });
+
+        self.client()?.request("textDocument/codeAction", params, Request::CodeAction)
+    }
+
+    // nothing is asked when the buffer has no language server
+    pub fn document_symbols(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.server_name().is_none() {
+            return Ok(());
+        }
+
+        let params = json!({"textDocument": {"uri": self.lsp.current}});
+        self.client()?.request("textDocument/documentSymbol", params, Request::Symbols)
+    }
+
+    pub fn lsp_complete(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let params = self.text_position();
+        let cursor = self.cursor;
+
+        self.client()?.request("textDocument/completion", params, Request::Completion(cursor))
+    }
+
+    pub fn format(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let params = json!({
+            "textDocument": {"uri": self.lsp.current},
+            "options": {"tabSize": self.options.get_int("tabsize"), "insertSpaces": true},
+        });
+
+        let uri = self.lsp.current.clone();
+        let client = self.client()?;
+        let version = client.documents.get(&uri).map(|document| document.version).unwrap_or(0);
+
+        client.request("textDocument/formatting", params, Request::Formatting(uri, version))
+    }
+
+    // diagnostics are shown in the sign column, themes style them with "diagnostic.error" and so on
+    fn diagnostic_signs(&mut self, uri: &str) {
+        let filename = match path(uri) {
+            Some(filename) => filename,
+            None => return,
+        };
+
+        let signs = self.lsp.diagnostics.get(uri).map(|diagnostics| &diagnostics[..]).unwrap_or(&[]).iter()
+            .map(|diagnostic| (diagnostic.start.0, Sign {
+                text: diagnostic.kind().chars().next().unwrap_or(' ').to_ascii_uppercase(),
+                group: format!("diagnostic.{}", diagnostic.kind()),
+                color: [1, 3, 4, 6][diagnostic.severity.clamp(1, 4) as usize - 1],
+                priority: 10 - diagnostic.severity.min(4) as usize,
+                message: Some(diagnostic.message.clone()),
+            }))
+            .collect();
+
+        self.signs.place("diagnostics", &filename, signs);
+    }
+
+    // the next or previous diagnostic of the buffer, the search wraps around
+    pub fn next_diagnostic(&mut self, forward: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let mut diagnostics = self.lsp.diagnostics().iter()
+            .map(|diagnostic| {
+                let line = self.buffer.get(diagnostic.start.0).map(|line| &line[..]).unwrap_or(&[]);
+                (Cursor { x: from_utf16(line, diagnostic.start.1), y: diagnostic.start.0 }, diagnostic)
+            })
+            .collect::<Vec<(Cursor, &Diagnostic)>>();
+
+        diagnostics.sort_by_key(|(cursor, _)| (cursor.y, cursor.x));
+
+        let position = (self.cursor.y, self.cursor.x);
+        let found = if forward {
+            diagnostics.iter().find(|(cursor, _)| (cursor.y, cursor.x) > position).or(diagnostics.first())
+        } else {
+            diagnostics.iter().rev().find(|(cursor, _)| (cursor.y, cursor.x) < position).or(diagnostics.last())
+        };
+
+        let (cursor, message) = match found {
+            Some((cursor, diagnostic)) => (*cursor, format!("{}: {}", diagnostic.kind(), diagnostic.message.lines().next().unwrap_or(""))),
+            None => return Err("No diagnostics".into()),
+        };
+
+        self.goto(cursor);
+        self.log(&message);
+        Ok(())
+    }
+
+    // the diagnostics of every file in a list buffer
+    pub fn list_diagnostics(&mut self, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        let mut found = self.lsp.diagnostics.iter()
+            .filter_map(|(uri, diagnostics)| Some((path(uri)?, diagnostics)))
+            .flat_map(|(filename, diagnostics)| diagnostics.iter().map(move |diagnostic| (filename.clone(), diagnostic.clone())))
+            .collect::<Vec<(String, Diagnostic)>>();
+
+        found.sort_by(|a, b| (&a.0, a.1.start).cmp(&(&b.0, b.1.start)));
+
+        if found.is_empty() {
+            return Err("No diagnostics".into());
+        }
+
+        let locations = found.iter()
+            .map(|(filename, diagnostic)| (filename.clone(), self.column(filename, diagnostic.start)))
+            .collect::<Vec<(String, Cursor)>>();
+
+        let lines = found.iter().zip(&locations)
+            .map(|((filename, diagnostic), (_, cursor))| format!("{}:{}:{}: {}: {}", filename, cursor.y + 1, cursor.x + 1, diagnostic.kind(), diagnostic.message.lines().next().unwrap_or("")))
+            .collect();
+
+        manager.open_list(self, "[Diagnostics]", List::new(lines, locations))
+    }
+
+    pub fn lsp_status(&self) -> String {
+        let mut running = self.lsp.clients.iter()
+            .map(|(name, client)| format!("{}: {}", name, client.command))
+            .collect::<Vec<String>>();
+
+        running.sort();
+
+        if running.is_empty() {
+            String::from("No language servers running")
+        } else {
+            running.join(", ")
+        }
+    }
+
+    // the character column of a position in a file that might not be open
+    fn column(&self, filename: &str, (y, column): (usize, usize)) -> Cursor {
+        let line = if uri(filename) == self.lsp.current {
+            self.buffer.get(y).cloned()
+        } else {
+            fs::read_to_string(filename).ok().and_then(|text| text.lines().nth(y).map(|line| line.chars().collect()))
+        };
+
+        Cursor {
+            x: line.map(|line| from_utf16(&line, column)).unwrap_or(0),
+            y,
+        }
+    }
+
+    fn location_lines(&self, locations: &[(String, Cursor)]) -> Vec<String> {
+        locations.iter()
+            .map(|(filename, cursor)| {
+                let text = if uri(filename) == self.lsp.current {
+                    self.buffer.get(cursor.y).map(|line| line.iter().collect::<String>())
+                } else {
+                    fs::read_to_string(filename).ok().and_then(|text| text.lines().nth(cursor.y).map(String::from))
+                };
+
+                format!("{}:{}:{}: {}", filename, cursor.y + 1, cursor.x + 1, text.unwrap_or_default().trim())
+            })
+            .collect()
+    }
+
+    // other files are opened in their buffers and left unsaved, the current buffer is shown again after
+    fn apply_workspace_edit(&mut self, edit: &Value, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        let mut changes: Vec<(String, Vec<Value>)> = Vec::new();
+
+        if let Some(map) = edit["changes"].as_object() {
+            changes.extend(map.iter().map(|(uri, edits)| (uri.clone(), edits.as_array().cloned().unwrap_or_default())));
+        }
+
+        for change in edit["documentChanges"].as_array().unwrap_or(&Vec::new()) {
+            if let Some(uri) = change["textDocument"]["uri"].as_str() {
+                changes.push((uri.to_string(), change["edits"].as_array().cloned().unwrap_or_default()));
+            }
+        }
+
+        let current = manager.current;
+
+        for (uri, edits) in &changes {
+            if *uri != self.lsp.current {
+                manager.open(self, &path(uri).ok_or(format!("Not a file: {}", uri))?)?;
+            }
+
+            self.apply_buffer_edits(edits);
+        }
+
+        if manager.current != current {
+            manager.save_buffer(self);
+            manager.current = current;
+            manager.reload(self)?;
+        }
+
+        self.log(&format!("Changed {} file{}", changes.len(), if changes.len() == 1 { "" } else { "s" }));
+        Ok(())
+    }
+
+    fn apply_buffer_edits(&mut self, edits: &[Value]) {
+        for (start, removed, inserted) in apply_edits(&mut self.buffer, edits) {
+            self.changed(start, removed, inserted);
+        }
+
+        self.cursor.y = self.cursor.y.min(self.buffer.len() - 1);
+        self.refresh = true;
+    }
+
+    pub fn run_code_action(&mut self, server: &str, action: &Value, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        if action["edit"].is_object() {
+            self.apply_workspace_edit(&action["edit"], manager)?;
+        }
+
+        // a command is either the action itself or a part of it
+        let command = if action["command"].is_string() { action.clone() } else { action["command"].clone() };
+
+        if let (Some(name), Some(client)) = (command["command"].as_str(), self.lsp.clients.get_mut(server)) {
+            let params = json!({"command": name, "arguments": command["arguments"].as_array().cloned().unwrap_or_default()});
+
+            client.next_id += 1;
+            let id = client.next_id;
+            client.send(json!({"jsonrpc": "2.0", "id": id, "method": "workspace/executeCommand", "params": params}))?;
+        }
+
+        Ok(())
+    }
+
+    // document symbols are nested, symbol information has a location instead of a range
+    fn symbol_items(&self, symbols: &Value, items: &mut Vec<(usize, Item)>) {
+        for symbol in symbols.as_array().unwrap_or(&Vec::new()) {
+            let range = if symbol["selectionRange"].is_object() { &symbol["selectionRange"] } else { &symbol["location"]["range"] };
+            let (y, column) = position(&range["start"]);
+
+            let kind = match symbol["kind"].as_u64().unwrap_or(0) {
+                2 => "mod",
+                5 => "class",
+                6 => "method",
+                8 => "field",
+                10 => "enum",
+                11 => "interface",
+                12 => "fn",
+                13 => "variable",
+                14 => "const",
+                22 => "variant",
+                23 => "struct",
+                26 => "type",
+                _ => "symbol",
+            };
+
+            let label = format!("{} {}", kind, symbol["name"].as_str().unwrap_or(""));
+            items.push((y, Item::new(label, &self.filename, self.column(&self.filename, (y, column)))));
+
+            self.symbol_items(&symbol["children"], items);
+        }
+    }
+
+    pub fn lsp_poll(&mut self, manager: &mut BufferManager) {
+        while let Ok((server, message)) = self.lsp.receiver.try_recv() {
+            if let Err(err) = self.handle_message(&server, message, manager) {
+                self.log(&err.to_string());
+            }
+        }
+    }
+
+    fn handle_message(&mut self, server: &str, message: Value, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        if message.is_null() {
+            self.lsp.clients.remove(server);
+            self.lsp.failed.insert(server.to_string());
+
+            return Err(format!("The {} language server exited", server).into());
+        }
+
+        let method = message["method"].as_str().unwrap_or("");
+
+        // a response to one of our requests
+        if method.is_empty() {
+            let request = message["id"].as_i64().and_then(|id| self.lsp.clients.get_mut(server)?.requests.remove(&id));
+
+            if let Some(error) = message["error"]["message"].as_str() {
+                return Err(error.into());
+            }
+
+            return match request {
+                Some(request) => self.handle_response(server, request, &message["result"], manager),
+                None => Ok(()),
+            };
+        }
+
+        // a request from the server
+        if !message["id"].is_null() {
+            let result = match method {
+                "workspace/configuration" => Value::Array(vec![Value::Null; message["params"]["items"].as_array().map(|items| items.len()).unwrap_or(0)]),
+                "workspace/applyEdit" => {
+                    let applied = self.apply_workspace_edit(&message["params"]["edit"], manager).is_ok();
+                    json!({"applied": applied})
+                },
+                _ => Value::Null,
+            };
+
+            if let Some(client) = self.lsp.clients.get_mut(server) {
+                client.reply(&message["id"], result)?;
+            }
+
+            return Ok(());
+        }
+
+        match method {
+            "textDocument/publishDiagnostics" => {
+                let uri = message["params"]["uri"].as_str().unwrap_or("").to_string();
+                let diagnostics = message["params"]["diagnostics"].as_array().unwrap_or(&Vec::new()).iter().map(Diagnostic::parse).collect();
+
+                self.lsp.diagnostics.insert(uri.clone(), diagnostics);
+                self.diagnostic_signs(&uri);
+                self.refresh = true;
+            },
+            "window/showMessage" => {
+                let text = message["params"]["message"].as_str().unwrap_or("").to_string();
+                self.log(&text);
+            },
+            _ => {},
+        }
+
+        Ok(())
+    }
+
+    fn handle_response(&mut self, server: &str, request: Request, result: &Value, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        match request {
+            Request::Initialize => {
+                if let Some(client) = self.lsp.clients.get_mut(server) {
+                    client.initialize(result["capabilities"].clone())?;
+                }
+            },
+            Request::Hover => {
+                let text = hover_text(&result["contents"]);
+
+                if text.trim().is_empty() {
+                    self.log("No information available");
+                } else {
+                    self.open_popup(Popup::text(text.trim()));
+                }
+            },
+            Request::Definition | Request::References => {
+                let locations = locations(result).into_iter()
+                    .map(|(filename, position)| {
+                        let cursor = self.column(&filename, position);
+                        (filename, cursor)
+                    })
+                    .collect::<Vec<(String, Cursor)>>();
+
+                match &locations[..] {
+                    [] => self.log("No locations found"),
+                    [(filename, cursor)] if matches!(request, Request::Definition) => {
+                        manager.open(self, filename)?;
+                        self.goto(*cursor);
+                    },
+                    _ => {
+                        let lines = self.location_lines(&locations);
+                        self.open_popup(Popup::list(lines, Pick::Locations(locations)));
+                    },
+                }
+            },
+            Request::Rename => {
+                if result.is_null() {
+                    return Err("Nothing to rename".into());
+                }
+
+                self.apply_workspace_edit(result, manager)?;
+            },
+            Request::CodeAction => {
+                let actions = result.as_array().cloned().unwrap_or_default();
+
+                if actions.is_empty() {
+                    self.log("No code actions available");
+                } else {
+                    let titles = actions.iter().map(|action| action["title"].as_str().unwrap_or("").to_string()).collect();
+                    self.open_popup(Popup::list(titles, Pick::Actions(server.to_string(), actions)));
+                }
+            },
+            Request::Completion(cursor) => {
+                // the answer is dropped when the cursor moved on in the meantime
+                if self.mode != Mode::Insert || self.cursor != cursor {
+                    return Ok(());
+                }
+
+                let items = if result.is_array() { result } else { &result["items"] };
+                let candidates = items.as_array().unwrap_or(&Vec::new()).iter()
+                    .filter_map(|item| item["insertText"].as_str().or(item["label"].as_str()))
+                    .map(String::from)
+                    .collect();
+
+                self.complete_with(candidates);
+            },
+            Request::Symbols => {
+                let mut items = Vec::new();
+                self.symbol_items(result, &mut items);
+                items.sort_by_key(|item: &(usize, Item)| item.0);
+
+                self.finder_symbols(items.into_iter().map(|(_, item)| item).collect());
+            },
+            Request::Formatting(uri, version) => {
+                let current = self.lsp.clients.get(server)
+                    .and_then(|client| client.documents.get(&uri))
+                    .map(|document| document.version == version)
+                    .unwrap_or(false);
+
+                if uri == self.lsp.current && current {
+                    self.apply_buffer_edits(result.as_array().unwrap_or(&Vec::new()));
+                }
+            },
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<Vec<char>> {
+        text.iter().map(|line| line.chars().collect()).collect()
+    }
+
+    #[test]
+    fn edits_are_applied_from_the_last_one() {
+        let edit = |start: (usize, usize), end: (usize, usize), text: &str| json!({
+            "range": {"start": {"line": start.0, "character": start.1}, "end": {"line": end.0, "character": end.1}},
+            "newText": text,
+        });
+
+        let mut buffer = lines(&["one", "two", "three"]);
+        let splices = apply_edits(&mut buffer, &[edit((0, 0), (0, 0), "zero\n"), edit((1, 1), (2, 2), "x"), edit((9, 0), (9, 0), "!")]);
+
+        assert_eq!(buffer, lines(&["zero", "one", "txree!"]));
+        assert_eq!(splices, vec![(2, 1, 1), (1, 2, 1), (0, 1, 2)]);
+    }
+
+    #[test]
+    fn diagnostics_move_with_their_lines() {
+        let diagnostic = |start: (usize, usize), end: (usize, usize)| Diagnostic { start, end, severity: 1, message: String::new() };
+        let rows = |lsp: &Lsp| lsp.diagnostics().iter().map(|diagnostic| (diagnostic.start, diagnostic.end)).collect::<Vec<_>>();
+
+        let mut lsp = Lsp::new(mpsc::channel().0);
+        lsp.current = String::from("file:///test.txt");
+        lsp.diagnostics.insert(lsp.current.clone(), vec![diagnostic((1, 2), (1, 4)), diagnostic((3, 0), (5, 1)), diagnostic((6, 1), (6, 3))]);
+        lsp.diagnostics.insert(String::from("file:///other.txt"), vec![diagnostic((1, 0), (1, 1))]);
+
+        lsp.insert_lines(2, 2);
+        assert_eq!(rows(&lsp), vec![((1, 2), (1, 4)), ((5, 0), (7, 1)), ((8, 1), (8, 3))]);
+
+        lsp.remove_lines(6, 3);
+        assert_eq!(rows(&lsp), vec![((1, 2), (1, 4)), ((5, 0), (6, 0))]);
+        assert_eq!(lsp.diagnostics["file:///other.txt"][0].start, (1, 0));
+    }
+
+    #[test]
+    fn columns_count_utf16_units() {
+        let line = "a😀b".chars().collect::<Vec<char>>();
+
+        assert_eq!(to_utf16(&line, 2), 3);
+        assert_eq!(from_utf16(&line, 3), 2);
+        assert_eq!(from_utf16(&line, 9), 3);
+    }
+}
@@ -1,18 +1,81 @@
 mod highlight;
 mod buffermanager;
-
-use std::process;
-use std::fs::File;
+mod options;
+mod script;
+mod input;
+mod keymap;
+mod session;
+mod render;
+mod theme;
+mod fold;
+mod bracket;
+mod autopair;
+mod indent;
+mod comment;
+mod complete;
+mod popup;
+mod lsp;
+mod signs;
+mod list;
+mod vcs;
+mod quickfix;
+mod grep;
+mod finder;
+mod browser;
+mod cmdline;
+mod ex;
+#[cfg(feature = "tree-sitter")]
+mod treesitter;
+
+use std::collections::{HashMap, VecDeque};
+use std::cell::{Ref, RefCell};
+use std::time::Duration;
+use std::env;
+use std::fs::{self, File};
 use std::io::BufReader;
 use std::io::prelude::*;
-use std::io::{self, Write};
+use std::io::Write;
+use std::path::Path;
 
-use console::{Term, Key};
+use console::Term;
 use buffermanager::BufferManager;
+use options::Options;
+use input::{Input, Key, KeyEvent, Modifiers, Event, MouseEvent, MouseKind, MouseButton};
+use keymap::{Keymap, Action};
+use session::Session;
+use render::{Frame, Renderer};
+use theme::{Theme, Depth};
+use fold::Folds;
+use complete::Completion;
+use popup::Popup;
+use lsp::Lsp;
+use signs::{Signs, Sign};
+use list::List;
+use quickfix::Quickfix;
+use finder::{Finder, Source};
+use browser::Operation;
+use cmdline::CommandLine;
+
+const MAX_MAPPING_DEPTH: usize = 1000;
+const SCROLL_LINES: usize = 3;
+const LINE_NUMBER_WIDTH: usize = 3;
+
+// a file in the configuration directory
+fn config_path(name: &str) -> Result<String, Box<dyn std::error::Error>> {
+    Ok(env::var("HOME")? + "/.config/te/" + name)
+}
+
+// the configuration directory is only created when something is written to it
+fn write_config(name: &str, contents: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let path = config_path(name)?;
+
+    if let Some(directory) = Path::new(&path).parent() {
+        fs::create_dir_all(directory)?;
+    }
 
-const BOTTOM_BAR: usize = 2;
-const TAB_SIZE:   usize = 4;
-const PARAGRAPH:  usize = 47;
+    fs::write(&path, contents).map_err(|err| format!("{}: {}", path, err))?;
+    Ok(())
+}
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 enum Mode {
@@ -75,7 +138,7 @@ impl Screen {
         self.y = 0;
     }
 }
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Cursor {
     x: usize,
     y: usize,
@@ -105,6 +168,14 @@ impl Matches {
     }
 }
 
+// the lines `start..start + removed` were replaced by `lines`
+#[derive(Clone, Debug)]
+pub struct Change {
+    pub start: usize,
+    pub removed: usize,
+    pub lines: Vec<String>,
+}
+
 pub struct Editor {
     buffer:    Vec<Vec<char>>,
     clipboard: Vec<Vec<char>>,
@@ -116,8 +187,44 @@ pub struct Editor {
     term:   Term,
     screen: Screen,
     syntax: highlight::Syntax,
+    theme:  Theme,
+    folds:  Folds,
+    options: Options,
+
+    input:     Input,
+    keymap:    Keymap,
+    pending:   Vec<(KeyEvent, bool)>,
+    // a sequence the keymap found, the main loop runs it
+    sequence:  Option<&'static str>,
+    typeahead: VecDeque<(KeyEvent, bool)>,
+    expansions: usize,
+    drag: Option<Cursor>,
+    brackets: Option<[(Cursor, usize); 2]>,
+    // where the pair was searched for, it is only searched again after a move or an edit
+    bracket_cursor: Option<(Cursor, Mode)>,
+    // the lines highlighted since the last edit, the frame and the bracket search share them
+    highlights: RefCell<HashMap<usize, Vec<(String, String)>>>,
+    operator: Option<usize>,
+    completion: Option<Completion>,
+    popup: Option<Popup>,
+    lsp: Lsp,
+    signs: Signs,
+    list: Option<List>,
+    quickfix: Quickfix,
+    finder: Option<Finder>,
+    confirm: Option<Operation>,
+    cmdline: CommandLine,
+    marks: HashMap<char, usize>,
+    // the edits since the last frame, the syntax tree follows them
+    changes: Vec<Change>,
+    // the buffer was edited since it was read or written
+    modified: bool,
+    renderer: Renderer,
+    // the colordepth setting and what it came out as, the terminal is only asked again when it changes
+    depth: (String, Depth),
 
     refresh: bool,
+    quit: bool,
 
     mode: Mode,
     log:  String,
@@ -125,6 +232,12 @@ pub struct Editor {
 
 impl Editor {
     pub fn new(filename: &str) -> Result<Editor, Box<dyn std::error::Error>> {
+        let options = Options::new();
+        let colordepth = options.get_str("colordepth");
+
+        let input = Input::new();
+        let lsp = Lsp::new(input.waker());
+
         Ok(Editor {
             buffer:    Vec::new(),
             clipboard: Vec::new(),
@@ -136,8 +249,38 @@ impl Editor {
             term:   Term::stdout(),
             screen: Screen::new(),
             syntax: highlight::Syntax::new(filename)?,
+            theme:  Theme::load(None)?,
+            folds:  Folds::new(),
+            options,
+
+            input,
+            keymap:    Keymap::new(),
+            pending:   Vec::new(),
+            sequence:  None,
+            typeahead: VecDeque::new(),
+            expansions: 0,
+            drag: None,
+            brackets: None,
+            bracket_cursor: None,
+            highlights: RefCell::new(HashMap::new()),
+            operator: None,
+            completion: None,
+            popup: None,
+            lsp,
+            signs: Signs::new(),
+            list: None,
+            quickfix: Quickfix::new(),
+            finder: None,
+            confirm: None,
+            cmdline: CommandLine::new(),
+            marks: HashMap::new(),
+            changes: Vec::new(),
+            modified: false,
+            renderer: Renderer::new(),
+            depth: (colordepth.clone(), Depth::detect(&colordepth)),
 
             refresh: true,
+            quit: false,
 
             mode:   Mode::Normal,
             log:    String::new(),
@@ -150,14 +293,24 @@ impl Editor {
     }
 
     pub fn open_file(&mut self, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if Path::new(file_path).is_dir() {
+            return self.open_directory(file_path);
+        }
+
         let fd = File::open(file_path)?;
-        let buf = BufReader::new(fd);
+        let lines = BufReader::new(fd).lines()
+            .map(|line| line.map(|line| line.chars().collect::<Vec<char>>()))
+            .collect::<Result<Vec<Vec<char>>, std::io::Error>>()?;
+
+        self.show_file(file_path, lines)
+    }
 
-        self.buffer.drain(..);
+    // shows `lines` as the text of the file, a buffer keeps its text while another one is shown
+    pub fn show_file(&mut self, file_path: &str, lines: Vec<Vec<char>>) -> Result<(), Box<dyn std::error::Error>> {
+        // the edits of the buffer that is left still reach the language server
+        self.flush_changes();
 
-        for line in buf.lines() {
-            self.buffer.push(line?.chars().collect::<Vec<char>>());
-        }
+        self.buffer = lines;
 
         if self.buffer.is_empty() {
             self.buffer.push(Vec::new());
@@ -165,6 +318,26 @@ impl Editor {
 
         self.filename = file_path.to_string();
         self.syntax = highlight::Syntax::new(&self.filename)?;
+        self.folds = Folds::new();
+        self.list = None;
+        self.replaced();
+
+        self.signs.open(file_path);
+        self.vcs_signs();
+        finder::remember_file(file_path);
+        self.lsp_attach();
+        Ok(())
+    }
+
+    fn colorscheme(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if name.is_empty() {
+            let name = self.theme.name.clone();
+            self.log(&name);
+            return Ok(());
+        }
+
+        self.theme = Theme::load(Some(name))?;
+        self.refresh = true;
         Ok(())
     }
 
@@ -173,150 +346,210 @@ impl Editor {
         self.refresh = true;
     }
 
-    fn render_bar(&self, manager: &BufferManager) -> String {
-        let mut bar = String::new();
-        let mut size = 0;
+    fn bottom_bar(&self) -> usize {
+        if self.options.get_bool("laststatus") { 2 } else { 1 }
+    }
+
+    fn bar_status(&self, manager: &BufferManager) -> (String, String) {
+        // Buffer number
+        let buf = format!(" [{}/{}] ", manager.current + 1, manager.buffers.len());
+
+        // Line number
+        let line = format!(" {}:{} ", self.cursor.y + 1, self.cursor.x + 1);
+
+        (buf, line)
+    }
+
+    fn render_bar(&self, frame: &mut Frame, y: usize, manager: &BufferManager) {
+        let bar = self.theme.style("bar");
 
         // Mode
-        bar += &self.syntax.colors.mode.apply_to(self.mode.as_string().to_uppercase()).to_string();
-        size += self.mode.as_string().len();
+        let mut x = frame.put(0, y, &self.mode.as_string().to_uppercase(), &self.theme.style("mode"));
 
         // Filename
-        bar += &self.syntax.colors.bar.apply_to(" ".to_string()  + &self.filename).to_string();
-        size += self.filename.len() + 1;
+        x = frame.put(x, y, &(" ".to_string() + &self.filename), &bar);
 
         // Filetype
-        bar += &self.syntax.colors.bar.apply_to(&format!(" [{}]", self.syntax.filetype)).to_string();
-        size += self.syntax.filetype.len() + 3;
-
-        // Buffer number
-        let buf = format!(" [{}/{}] ", manager.current + 1, manager.buffers.len());
-        size += buf.len();
+        x = frame.put(x, y, &format!(" [{}]", self.syntax.filetype), &bar);
 
-        // Line number
-        let line = format!(" {}:{} ", self.cursor.y + 1, self.cursor.x + 1);
-        size += line.len();
+        let (buf, line) = self.bar_status(manager);
 
         // Middle padding
-        bar += &self.syntax.colors.bar.apply_to((size..self.screen.width).map(|_| " ").collect::<String>()).to_string();
+        x = frame.put(x, y, &" ".repeat(self.screen.width.saturating_sub(x + buf.len() + line.len())), &bar);
 
         // Buffer number
-        bar += &self.syntax.colors.bar.apply_to(&buf).to_string();
+        x = frame.put(x, y, &buf, &bar);
 
         // Line number
-        bar += &self.syntax.colors.bar.apply_to(&line).to_string();
-
-        bar
-    }
-
-    fn refresh_bar(&mut self, manager: &BufferManager) -> Result<(), Box<dyn std::error::Error>> {
-        self.term.move_cursor_to(0, self.screen.height - 2)?;
-        self.term.write_line(&self.render_bar(manager))?;
-        Ok(())
+        frame.put(x, y, &line, &bar);
     }
 
-    fn render_log(&self) -> String {
-        let mut output = String::new();
-
-        // Message
-        output += &self.syntax.colors.default.apply_to(&self.log).to_string();
+    fn render_log(&self, frame: &mut Frame, y: usize) {
+        let default = self.theme.style("default");
 
-        // Padding
-        output += &self.syntax.colors.default.apply_to((self.log.len()..self.screen.width).map(|_| " ").collect::<String>()).to_string();
-
-        output
+        let x = frame.put(0, y, &self.log, &default);
+        frame.fill(x, y, &default);
     }
 
-    fn render_line_number(&self, index: usize) -> String {
-        let cursor_position = self.cursor.y - self.screen.y;
+    fn render_line_number(&self, frame: &mut Frame, index: usize, line: usize) -> usize {
+        let cursor_position = self.cursor_row();
 
-        return if index > cursor_position {
+        let number = if index > cursor_position {
             // UNDER
-            self.syntax.colors.line_numbers.apply_to(format!("{:02} ", index - cursor_position)).to_string()
+            format!("{:02} ", index - cursor_position)
         } else if index < cursor_position {
             // OVER
-            self.syntax.colors.line_numbers.apply_to(format!("{:02} ", cursor_position - index)).to_string()
+            format!("{:02} ", cursor_position - index)
         } else {
-            self.syntax.colors.line_numbers.apply_to("-> ").to_string()
+            String::from("-> ")
         };
+
+        let x = self.render_sign(frame, index, line);
+        frame.put(x, index, &number, &self.theme.style("line_numbers"));
+
+        self.gutter()
     }
 
-    fn refresh_line_numbers(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.term.move_cursor_to(0, 0)?;
+    fn render_line(&mut self, frame: &mut Frame, y: usize, index: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let default = self.theme.style("default");
+
+        let mut x = self.render_line_number(frame, y, index);
+
+        if self.buffer.len() <= index {
+            x = frame.put(x, y, &self.options.get_str("fillchar"), &default);
+        } else if let Some(fold) = self.folds.closed_at(index) {
+            // a closed fold is drawn as its first line and the number of lines in it
+            let line = self.buffer[index].iter().collect::<String>();
+            let summary = format!("+--{:>3} lines: {} ", fold.end - fold.start + 1, line.trim());
+            let style = self.theme.style("fold");
+
+            x = frame.put(x, y, &summary, &style);
+            frame.fill(x, y, &style);
+            return Ok(());
+        } else if !self.buffer[index].is_empty() {
+            // the whole line is highlighted so matches that start left of the screen keep their group
+            let mut skip = self.screen.x;
+
+            for (group, text) in self.highlighted(index).iter() {
+                let length = text.chars().count();
+
+                if skip >= length {
+                    skip -= length;
+                    continue;
+                }
+
+                x = frame.put(x, y, &text.chars().skip(skip).collect::<String>(), &self.theme.resolve(group, Some(&self.syntax.links)));
+                skip = 0;
+            }
+
+            // the matching brackets keep their colors and are underlined unless the theme styles them
+            for (position, length) in self.brackets.iter().flatten().filter(|(position, _)| position.y == index && position.x >= self.screen.x) {
+                let column = self.gutter() + position.x - self.screen.x;
+                let text = self.buffer[index][position.x..position.x + length].iter().collect::<String>();
+
+                let style = if self.theme.has("bracket") {
+                    self.theme.style("bracket")
+                } else {
+                    theme::Style { bold: true, underline: true, ..frame.style_at(column, y) }
+                };
 
-        for index in 0..self.screen.height - BOTTOM_BAR {
-            self.term.write_line(&self.render_line_number(index))?;
+                frame.put(column, y, &text, &style);
+            }
         }
 
+        x = self.render_virtual_text(frame, x, y, index);
+
+        // fill the empty space with background color
+        frame.fill(x, y, &default);
         Ok(())
     }
 
-    fn empty_line(&self, index: usize) -> bool {
-        if self.buffer.len() <= index {
-            return true;
-        } else if self.buffer[index].len() == 0 {
-            return true;
+    // hands the edits since the last frame to what follows the text, folds are only found again after one
+    fn flush_changes(&mut self) {
+        if self.changes.is_empty() {
+            return;
         }
-        return false;
+
+        let changes = std::mem::take(&mut self.changes);
+        self.syntax.update(&changes);
+        self.highlights.get_mut().clear();
+        self.update_folds();
+        self.lsp_sync(&changes);
     }
 
-    fn render(&mut self, manager: &BufferManager) -> Result<(), Box<dyn std::error::Error>> {
-        if !self.refresh {
-            self.refresh_line_numbers()?;
-            self.refresh_bar(manager)?;
-            self.term.move_cursor_to(self.cursor.x + 3, self.cursor.y - self.screen.y)?;
-            return Ok(()); // return if refresh is false
+    // the groups of line `index`, kept until the text changes
+    fn highlighted(&self, index: usize) -> Ref<'_, Vec<(String, String)>> {
+        if !self.highlights.borrow().contains_key(&index) {
+            let line = self.buffer[index].iter().collect::<String>();
+            let spans = self.syntax.highlight(index, &line).into_iter().map(|(group, text)| (group.to_string(), text)).collect();
+
+            self.highlights.borrow_mut().insert(index, spans);
         }
 
-        self.term.hide_cursor()?;
-        self.term.move_cursor_to(0, 0)?;
+        Ref::map(self.highlights.borrow(), |highlights| &highlights[&index])
+    }
 
-        for index in self.screen.y..self.screen.y + self.screen.height - BOTTOM_BAR {
-            if self.empty_line(index) {
-                // fill the empty space with background color
-                let mut line = (0..self.screen.width - 3 /* Length of line number */).map(|_| " ").collect::<String>();
+    fn render(&mut self, manager: &BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        let mut frame = Frame::new(self.screen.width, self.screen.height);
 
-                if self.buffer.len() <= index {
-                    line.pop();
-                    line = format!("{}{}", self.render_line_number(index - self.screen.y), console::style(String::from("~") + &line).on_color256(self.syntax.colors.background));
-                } else {
-                    line = format!("{}{}", self.render_line_number(index - self.screen.y), console::style(line).on_color256(self.syntax.colors.background));
-                }
+        let edited = !self.changes.is_empty();
+        self.flush_changes();
 
-                self.term.write_line(&line)?;
-            } else {
-                let length = self.buffer[index].len();
-                let line = &self.buffer[index].clone()[self.screen.x..length.clamp(self.screen.x, self.screen.x + self.screen.width)];
-
-                let mut line = match self.syntax.highlight(line.into_iter().collect()) {
-                    Ok(line) => line,
-                    Err(_) => {
-                        return Err("syntax highlighting has failed".into());
-                    },
-                };
+        if edited || self.bracket_cursor != Some((self.cursor, self.mode)) {
+            self.bracket_cursor = Some((self.cursor, self.mode));
+
+            let brackets = if self.mode == Mode::Command { None } else { self.bracket_pair() };
+            if brackets != self.brackets {
+                self.brackets = brackets;
+                self.refresh = true;
+            }
+        }
 
-                // relative line numbers
-                line = self.render_line_number(index - self.screen.y) + &line;
+        // when nothing in the text changed the lines of the last frame are reused
+        let previous = if self.refresh { None } else { self.renderer.previous.clone() };
 
-                // add padding
-                let padding = &(0..self.screen.width - self.buffer[index].len() - 4 /* Length of line number */).map(|_| " ").collect::<String>();
-                line = line + &format!("{}", console::style(padding).on_color256(self.syntax.colors.background));
+        let mut line = self.screen.y;
 
-                self.term.write_line(&line)?;
+        for y in 0..self.screen.height - self.bottom_bar() {
+            match &previous {
+                Some(previous) => {
+                    frame.copy_row(previous, y);
+                    self.render_line_number(&mut frame, y, line);
+                },
+                None => self.render_line(&mut frame, y, line)?,
             }
+
+            line = self.folds.next_line(line);
         }
 
+        self.render_completion(&mut frame);
+        self.render_popup(&mut frame);
+        self.render_finder(&mut frame);
+
         // bar
-        self.term.write_line(&self.render_bar(manager))?;
+        if self.options.get_bool("laststatus") {
+            self.render_bar(&mut frame, self.screen.height - 2, manager);
+        }
+
+        self.render_wildmenu(&mut frame);
 
         // log
-        // NOTE: print is used here because we dont want a newline
-        self.term.clear_line()?;
-        print!("{}", self.render_log());
-        io::stdout().flush()?;
+        self.render_log(&mut frame, self.screen.height - 1);
 
-        self.term.show_cursor()?;
-        self.term.move_cursor_to(self.cursor.x + 3, self.cursor.y - self.screen.y)?;
+        let cursor = if let Some(cursor) = self.finder_cursor() {
+            cursor
+        } else if self.mode == Mode::Command {
+            (self.cmdline.cursor(), self.screen.height - 1)
+        } else {
+            (self.cursor.x + self.gutter(), self.cursor_row())
+        };
+
+        let colordepth = self.options.get_str("colordepth");
+        if colordepth != self.depth.0 {
+            self.depth = (colordepth.clone(), Depth::detect(&colordepth));
+        }
+
+        self.renderer.draw(frame, cursor, self.depth.1, self.options.get_bool("syncoutput"))?;
 
         self.refresh = false;
         Ok(())
@@ -340,26 +573,46 @@ impl Editor {
             },
             Direction::Up => {
                 if self.cursor.y > 0 {
-                    if self.cursor.y <= self.screen.y {
-                        self.screen.y -= 1;
-                        self.refresh = true;
-                    }
-                    self.cursor.y -= 1;
+                    self.cursor.y = self.folds.previous_line(self.cursor.y);
+                    self.scroll_to_cursor();
                 }
             },
             Direction::Down => {
-                if self.cursor.y < self.buffer.len() - 1 {
-                    if self.cursor.y >= self.screen.height + self.screen.y - BOTTOM_BAR - 1 {
-                        self.screen.y += 1;
-                        self.refresh = true;
-                    }
-                    self.cursor.y += 1;
+                // a closed fold is skipped as a whole
+                let next = self.folds.next_line(self.cursor.y);
+
+                if next < self.buffer.len() {
+                    self.cursor.y = next;
+                    self.scroll_to_cursor();
                 }
             },
         }
     }
 
+    // the screen line of the cursor, closed folds take up one line
+    fn cursor_row(&self) -> usize {
+        self.folds.rows(self.screen.y, self.cursor.y)
+    }
+
+    fn scroll_to_cursor(&mut self) {
+        let height = self.screen.height - self.bottom_bar();
+
+        if self.cursor.y < self.screen.y {
+            self.screen.y = self.folds.visible_start(self.cursor.y);
+            self.refresh = true;
+        }
+
+        while self.cursor_row() >= height {
+            self.screen.y = self.folds.next_line(self.screen.y);
+            self.refresh = true;
+        }
+    }
+
     fn clamp_cursor(&mut self) {
+        // the cursor can not be inside of a closed fold
+        self.cursor.y = self.folds.visible_start(self.cursor.y);
+        self.screen.y = self.folds.visible_start(self.screen.y);
+
         if self.cursor.x > self.buffer[self.cursor.y].len() || self.clamp > self.buffer[self.cursor.y].len() {
             self.cursor.x = self.buffer[self.cursor.y].len();
         } else if self.clamp < self.buffer[self.cursor.y].len() {
@@ -367,17 +620,90 @@ impl Editor {
         }
     }
 
+    // every edit reports the lines it replaced, `inserted` lines now start at `start`
+    fn changed(&mut self, start: usize, removed: usize, inserted: usize) {
+        if inserted > removed {
+            let (at, count) = (start + removed, inserted - removed);
+
+            self.folds.insert_lines(at, count);
+            self.signs.insert_lines(at, count);
+            self.lsp.insert_lines(at, count);
+
+            for line in self.marks.values_mut().filter(|line| **line >= at) {
+                *line += count;
+            }
+        } else if removed > inserted {
+            let (at, count) = (start + inserted, removed - inserted);
+
+            self.folds.remove_lines(at, count);
+            self.signs.remove_lines(at, count);
+            self.lsp.remove_lines(at, count);
+
+            // the marks of removed lines go with them
+            self.marks.retain(|_, line| *line < at || *line >= at + count);
+            for line in self.marks.values_mut().filter(|line| **line >= at) {
+                *line -= count;
+            }
+        }
+
+        let lines = self.buffer[start..start + inserted].iter().map(|line| line.iter().collect()).collect();
+        self.changes.push(Change { start, removed, lines });
+        self.modified = true;
+        self.refresh = true;
+    }
+
+    // the whole buffer is new, like when a file is opened
+    fn replaced(&mut self) {
+        let lines = self.buffer.iter().map(|line| line.iter().collect()).collect();
+        self.changes = vec![Change { start: 0, removed: usize::MAX, lines }];
+        self.modified = false;
+        self.refresh = true;
+    }
+
     fn insert(&mut self, character: char) {
         self.buffer[self.cursor.y].insert(self.cursor.x, character);
+        self.changed(self.cursor.y, 1, 1);
         self.move_cursor(Direction::Right);
         self.refresh = true;
     }
 
+    // inserts a block of text as one edit, without indenting new lines
+    fn insert_text(&mut self, text: &str) {
+        let tab = " ".repeat(self.options.get_int("tabsize"));
+        let text = text.replace("\r\n", "\n").replace('\r', "\n").replace('\t', &tab);
+
+        let start = self.cursor.y;
+        let tail = self.buffer[self.cursor.y].split_off(self.cursor.x);
+
+        for (index, line) in text.split('\n').enumerate() {
+            if index > 0 {
+                self.cursor.y += 1;
+                self.cursor.x = 0;
+                self.buffer.insert(self.cursor.y, Vec::new());
+            }
+
+            self.buffer[self.cursor.y].extend(line.chars());
+            self.cursor.x += line.chars().count();
+        }
+
+        self.buffer[self.cursor.y].extend(tail);
+        self.changed(start, 1, self.cursor.y - start + 1);
+
+        // scroll the end of the pasted text into view
+        self.scroll_to_cursor();
+
+        self.clamp = self.cursor.x;
+        self.refresh = true;
+    }
+
     fn remove(&mut self) {
+        self.remove_closing();
+
         if self.cursor.x != 0 {
             // delete char
             self.move_cursor(Direction::Left);
             self.buffer[self.cursor.y].remove(self.cursor.x);
+            self.changed(self.cursor.y, 1, 1);
         } else if self.cursor.y != 0 {
             let line_len = self.buffer[self.cursor.y - 1].len();
 
@@ -387,6 +713,7 @@ impl Editor {
 
             // remove line under
             self.buffer.remove(self.cursor.y);
+            self.changed(self.cursor.y - 1, 2, 1);
             self.move_cursor(Direction::Up);
 
             // move cursor to where the old length of the line over used to be
@@ -396,46 +723,24 @@ impl Editor {
         self.refresh = true;
     }
 
-    fn indentation(&self) -> usize {
-        for (index, character) in self.buffer[self.cursor.y - 1].iter().enumerate() {
-            if *character != ' ' {
-                return index;
-            }
-        }
-
-        return 0;
-    }
-
     fn newline(&mut self, cut: bool) {
-        if self.buffer.len() == 1 || self.cursor.y >= self.buffer.len() - 1 {
-            self.buffer.push(Vec::new());
-            self.move_cursor(Direction::Down);
-        } else {
-            self.move_cursor(Direction::Down);
-            self.buffer.insert(self.cursor.y, Vec::new());
-        }
+        self.folds.reveal(self.cursor.y);
 
-        if cut {
-            self.buffer[self.cursor.y] = self.buffer[self.cursor.y - 1][self.cursor.x..self.buffer[self.cursor.y - 1].len()].to_vec();
-            self.buffer[self.cursor.y - 1] = self.buffer[self.cursor.y - 1][0..self.cursor.x].to_vec();
-            self.cursor.x = 0;
-        } else {
-            let indentation = self.indentation();
-            self.buffer[self.cursor.y] = " ".repeat(indentation)
-                .as_bytes()
-                .iter()
-                .map(|e| *e as char)
-                .collect::<Vec<char>>();
+        let tail = if cut { self.buffer[self.cursor.y].split_off(self.cursor.x) } else { Vec::new() };
+        self.buffer.insert(self.cursor.y + 1, tail);
+        self.changed(self.cursor.y, 1, 2);
+        self.move_cursor(Direction::Down);
 
-            self.cursor.x = indentation;
-        }
+        let indentation = self.indentation(self.cursor.y);
+        self.set_indentation(self.cursor.y, indentation);
+        self.cursor.x = indentation;
 
         self.clamp = self.cursor.x;
         self.refresh = true;
     }
 
     fn range(&self, visual: usize, cursor: usize) -> (std::ops::Range<usize>, Direction) {
-        return if visual < cursor {
+        if visual < cursor {
             if cursor == visual {
                 (visual..cursor + 1, Direction::Down)
             } else {
@@ -447,19 +752,23 @@ impl Editor {
             } else {
                 (cursor..visual, Direction::Up)
             }
-        };
+        }
     }
 
     fn delete(&mut self, visual: &Visual) {
         if visual.select_line {
             if self.buffer.len() == 1 {
                 self.buffer[0] = Vec::new();
+                self.changed(0, 1, 1);
             } else if self.cursor.y >= self.buffer.len() - 1 {
                 self.move_cursor(Direction::Up);
                 self.buffer.pop();
+                self.changed(self.buffer.len(), 1, 0);
             } else {
                 let range = self.range(visual.y, self.cursor.y);
-                let deleted = self.buffer.drain(range.0).collect::<Vec<Vec<char>>>();
+
+                let deleted = self.buffer.drain(range.0.clone()).collect::<Vec<Vec<char>>>();
+                self.changed(range.0.start, range.0.len(), 0);
 
                 if range.1 != Direction::Up {
                     for _ in deleted {
@@ -471,6 +780,7 @@ impl Editor {
             let range = self.range(visual.x, self.cursor.x);
 
             let deleted = self.buffer[self.cursor.y].drain(range.0).collect::<Vec<char>>();
+            self.changed(self.cursor.y, 1, 1);
 
             if range.1 != Direction::Up {
                 for _ in deleted {
@@ -486,12 +796,16 @@ impl Editor {
             for line in self.clipboard.iter().rev() {
                 self.buffer.insert(self.cursor.y, line.clone());
             }
+
+            self.changed(self.cursor.y, 0, self.clipboard.len());
         } else {
             let content = self.clipboard[0].clone();
             for character in &content {
                 self.buffer[self.cursor.y].insert(self.cursor.x, *character);
                 self.move_cursor(Direction::Right);
             }
+
+            self.changed(self.cursor.y, 1, 1);
         }
     }
 
@@ -504,21 +818,36 @@ impl Editor {
     }
 
     fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let mut fd = File::create(&self.filename)?;
-        for line in &self.buffer {
+        if self.list.is_some() {
+            return Err(format!("`{}` is not a file", self.filename).into());
+        }
+
+        self.write_lines(&self.filename, 0, self.buffer.len() - 1)
+    }
+
+    fn write_lines(&self, path: &str, start: usize, end: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let newline: &[u8] = if self.options.get_str("fileformat") == "dos" { b"\r\n" } else { b"\n" };
+
+        let mut fd = File::create(path)?;
+        for line in &self.buffer[start..=end] {
             fd.write_all(&line.iter().map(|e| *e as u8).collect::<Vec<u8>>())?;
-            fd.write_all(b"\n")?;
+            fd.write_all(newline)?;
         }
 
         Ok(())
     }
 
-    fn log_save(&mut self) {
-        if let Err(_) = self.save() {
+    fn log_save(&mut self) -> bool {
+        if self.save().is_err() {
             self.log(&format!("failed to write to `{}`", self.filename));
-        } else {
-            self.log(&format!("wrote to `{}`", self.filename));
+            return false;
         }
+
+        self.log(&format!("wrote to `{}`", self.filename));
+        self.modified = false;
+        self.vcs_signs();
+        self.lsp_saved();
+        true
     }
 
     fn search(&mut self, query: &str) {
@@ -534,6 +863,11 @@ impl Editor {
             }
         }
 
+        let hits = self.matches.matches.iter()
+            .map(|hit| (hit.y, Sign { text: '/', group: String::from("search"), color: 5, priority: 2, message: None }))
+            .collect();
+        self.signs.place("search", &self.filename, hits);
+
         self.goto_match();
     }
 
@@ -551,102 +885,84 @@ impl Editor {
         }
     }
 
+    // places the cursor at a position found somewhere else, like a location from a language server
+    pub fn goto(&mut self, cursor: Cursor) {
+        self.cursor.y = cursor.y.min(self.buffer.len() - 1);
+        self.cursor.x = cursor.x.min(self.buffer[self.cursor.y].len());
+        self.clamp = self.cursor.x;
+
+        self.folds.reveal(self.cursor.y);
+        self.scroll_to_cursor();
+        self.refresh = true;
+    }
+
     fn goto_match(&mut self) {
         if self.matches.index < self.matches.matches.len() {
             self.cursor = self.matches.matches[self.matches.index];
+            self.folds.reveal(self.cursor.y);
             self.screen.y = self.cursor.y;
             self.screen.x = self.cursor.x;
             self.refresh = true;
         }
     }
 
-    fn command(&mut self, cmd: String, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
-        match cmd.as_str() {
-            ":E" => {
-                self.log_save();
-            },
-            ":EQ" => {
-                self.log_save();
-                process::exit(0);
-            },
-            ":q" => {
-                process::exit(0);
-            },
-            ":qb" => {
-                manager.close_buffer(self);
-            },
-            _ => {
-                if cmd.starts_with(":/") {
-                    self.search(&cmd[2..]);
-                } else if cmd.starts_with(":O") {
-                    self.open_file(&cmd[3..])?;
-                    self.reset();
-
-                    manager.load_buffer(&self);
-                    manager.next_buffer(self)?;
-                } else {
-                    self.log(&format!("Unknown command: `{cmd}`"));
-                }
-            },
-        }
-
-        Ok(())
-    }
-
     fn move_by_paragraph(&mut self, direction: Direction) {
+        let paragraph = self.options.get_int("paragraph");
+
+        // closed folds count as a single line
         if direction == Direction::Up {
-            if self.cursor.y < PARAGRAPH {
-                self.cursor.y = 0;
-                self.screen.y = 0;
-            } else {
-                self.cursor.y -= PARAGRAPH;
-                self.screen.y = self.cursor.y;
+            for _ in 0..paragraph {
+                self.cursor.y = self.folds.previous_line(self.cursor.y);
             }
+            self.screen.y = self.cursor.y;
         } else if direction == Direction::Down {
-            if self.cursor.y + PARAGRAPH >= self.buffer.len() {
-                self.cursor.y = self.buffer.len() - 1;
-                self.screen.y = self.buffer.len() - 1;
-            } else {
-                self.cursor.y += PARAGRAPH;
-                self.screen.y += PARAGRAPH;
+            let mut moved = 0;
+
+            while moved < paragraph && self.folds.next_line(self.cursor.y) < self.buffer.len() {
+                self.cursor.y = self.folds.next_line(self.cursor.y);
+                self.screen.y = self.folds.next_line(self.screen.y);
+                moved += 1;
+            }
+
+            if moved < paragraph {
+                self.screen.y = self.cursor.y;
             }
         }
     }
 
-    fn handle_escape(&mut self, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
-        let modifier = self.term.read_key()?;
-        let arrow = self.term.read_key()?;
+    fn handle_modifier(&mut self, event: &KeyEvent, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        let arrow = &event.key;
 
-        match modifier {
-            Key::Char('2') => {
+        match event.modifiers {
+            Modifiers::SHIFT => {
                 // Key: Shift
-                if arrow == Key::Char('C') {
+                if *arrow == Key::ArrowRight {
                     // Key: Right Arrow
                     self.cursor.x = self.syntax.next_token(self.buffer[self.cursor.y].iter().collect::<String>(), self.cursor.x)?;
                     self.clamp = self.cursor.x;
-                } else if arrow == Key::Char('D') {
+                } else if *arrow == Key::ArrowLeft {
                     // Key: Left Arrow
                     self.cursor.x = self.syntax.previous_token(self.buffer[self.cursor.y].iter().collect::<String>(), self.cursor.x)?;
                     self.clamp = self.cursor.x;
-                } else if arrow == Key::Char('A') {
+                } else if *arrow == Key::ArrowUp {
                     // Key: Up Arrow
                     self.move_by_paragraph(Direction::Up);
                     self.refresh = true;
-                } else if arrow == Key::Char('B') {
+                } else if *arrow == Key::ArrowDown {
                     // Key: Down Arrow
                     self.move_by_paragraph(Direction::Down);
                     self.refresh = true;
                 }
             },
-            Key::Char('5') => {
+            Modifiers::CTRL => {
                 // Key: Ctrl
-                manager.save_buffer(&self);
+                manager.save_buffer(self);
                 let old = manager.current;
 
-                if arrow == Key::Char('C') {
+                if *arrow == Key::ArrowRight {
                     // Key: Right Arrow
                     manager.next_buffer(self)?;
-                } else if arrow == Key::Char('D') {
+                } else if *arrow == Key::ArrowLeft {
                     // Key: Left Arrow
                     manager.previous_buffer(self)?;
                 }
@@ -661,10 +977,212 @@ impl Editor {
         Ok(())
     }
 
+    fn expand(&mut self, action: Action, noremap: bool, manager: &mut BufferManager) {
+        self.pending = Vec::new();
+        self.expansions += 1;
+
+        if self.expansions > MAX_MAPPING_DEPTH {
+            self.typeahead = VecDeque::new();
+            self.log("Recursive mapping");
+            return;
+        }
+
+        match action {
+            Action::Keys(keys) => {
+                for key in keys.into_iter().rev() {
+                    self.typeahead.push_front((key, !noremap));
+                }
+            },
+            Action::Command(cmd) => {
+                if let Err(err) = self.command(cmd, manager) {
+                    self.log(&err.to_string());
+                }
+            },
+            Action::Sequence(sequence) => {
+                self.sequence = Some(sequence);
+            },
+        }
+    }
+
+    // the pending keys are only remapped when all of them can be
+    fn lookup_pending(&self) -> keymap::Lookup<'_> {
+        let keys = self.pending.iter().map(|(key, _)| key.clone()).collect::<Vec<KeyEvent>>();
+        self.keymap.lookup(self.mode, &keys, self.pending.iter().all(|(_, remap)| *remap))
+    }
+
+    fn flush_pending(&mut self, manager: &mut BufferManager) -> Option<(KeyEvent, bool)> {
+        if let Some(mapping) = self.lookup_pending().exact.cloned() {
+            self.expand(mapping.action, mapping.noremap, manager);
+            return None;
+        }
+
+        // the pending keys did not form a mapping, the first one is used as is and the rest are retried
+        let mut pending = std::mem::take(&mut self.pending).into_iter();
+        let first = pending.next();
+
+        for entry in pending.rev() {
+            self.typeahead.push_front(entry);
+        }
+
+        first
+    }
+
+    fn next_event(&mut self, manager: &mut BufferManager) -> Result<Option<Event>, Box<dyn std::error::Error>> {
+        let escape = Duration::from_millis(self.options.get_int("ttimeoutlen") as u64);
+
+        let (event, remap) = match self.typeahead.pop_front() {
+            Some(entry) => entry,
+            None => {
+                let timeout = if self.pending.is_empty() {
+                    self.expansions = 0;
+                    None
+                } else {
+                    Some(Duration::from_millis(self.options.get_int("timeoutlen") as u64))
+                };
+
+                match self.input.read(timeout, escape)? {
+                    Some(Event::Key(event)) => (event, true),
+                    Some(event) => return Ok(Some(event)),
+                    None if self.pending.is_empty() => return Ok(None),
+                    None => return Ok(self.flush_pending(manager).map(|(event, _)| Event::Key(event))),
+                }
+            },
+        };
+
+        if self.mode == Mode::Command || self.finder.is_some() {
+            return Ok(self.split_chord(event, remap));
+        }
+
+        self.pending.push((event, remap));
+
+        if self.lookup_pending().prefix {
+            return Ok(None);
+        }
+
+        Ok(self.flush_pending(manager).and_then(|(event, remap)| self.split_chord(event, remap)))
+    }
+
+    // terminals send alt as escape before the key, a chord without a mapping was escape typed quickly
+    fn split_chord(&mut self, event: KeyEvent, remap: bool) -> Option<Event> {
+        match event.key {
+            Key::Char(character) if event.modifiers == Modifiers::ALT => {
+                self.typeahead.push_front((KeyEvent::new(Key::Char(character)), remap));
+                self.typeahead.push_front((KeyEvent::new(Key::Escape), remap));
+                None
+            },
+            _ => Some(Event::Key(event)),
+        }
+    }
+
+    fn mouse_position(&self, mouse: &MouseEvent) -> Cursor {
+        let y = self.folds.line_at(self.screen.y, mouse.y).min(self.buffer.len() - 1);
+        let x = (self.screen.x + mouse.x.saturating_sub(self.gutter())).min(self.buffer[y].len());
+
+        Cursor {
+            x,
+            y,
+        }
+    }
+
+    fn scroll(&mut self, direction: Direction) {
+        let height = self.screen.height - self.bottom_bar();
+
+        for _ in 0..SCROLL_LINES {
+            if direction == Direction::Up {
+                self.screen.y = self.folds.previous_line(self.screen.y);
+            } else if self.folds.next_line(self.screen.y) < self.buffer.len() {
+                self.screen.y = self.folds.next_line(self.screen.y);
+            }
+        }
+
+        // keep the cursor inside of the visible lines
+        let bottom = self.folds.line_at(self.screen.y, height - 1).min(self.buffer.len() - 1);
+        self.cursor.y = self.folds.visible_start(self.cursor.y.clamp(self.screen.y, bottom));
+        self.refresh = true;
+    }
+
+    fn handle_mouse(&mut self, mouse: &MouseEvent, visual: &mut Visual, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        let text_height = self.screen.height - self.bottom_bar();
+
+        match mouse.kind {
+            MouseKind::ScrollUp => self.scroll(Direction::Up),
+            MouseKind::ScrollDown => self.scroll(Direction::Down),
+            MouseKind::Press(MouseButton::Left) if mouse.y < text_height => {
+                self.cursor = self.mouse_position(mouse);
+                self.clamp = self.cursor.x;
+                self.drag = Some(self.cursor);
+
+                if self.mode == Mode::Visual {
+                    self.mode = Mode::Normal;
+                }
+                self.refresh = true;
+            },
+            MouseKind::Press(MouseButton::Left) if mouse.y == self.screen.height - 2 && self.options.get_bool("laststatus") => {
+                let (buf, line) = self.bar_status(manager);
+                let end = self.screen.width.saturating_sub(line.len());
+
+                // clicking the buffer number switches to the next buffer
+                if (end.saturating_sub(buf.len())..end).contains(&mouse.x) && manager.buffers.len() > 1 {
+                    manager.save_buffer(self);
+                    manager.current = (manager.current + 1) % manager.buffers.len();
+                    manager.reload(self)?;
+                }
+            },
+            MouseKind::Drag(MouseButton::Left) if mouse.y < text_height => {
+                if let Some(anchor) = self.drag {
+                    if self.mode != Mode::Visual {
+                        *visual = Visual::new(&anchor, false);
+                        self.mode = Mode::Visual;
+                    }
+
+                    // a character selection can not span lines, dragging onto another line selects whole lines
+                    self.cursor = self.mouse_position(mouse);
+                    self.clamp = self.cursor.x;
+                    visual.select_line = self.cursor.y != anchor.y;
+                    self.refresh = true;
+                }
+            },
+            MouseKind::Release(_) => {
+                self.drag = None;
+            },
+            _ => {},
+        }
+
+        Ok(())
+    }
+
+    fn run_sequence(&mut self, sequence: &str, visual: &Visual) -> Result<(), Box<dyn std::error::Error>> {
+        match sequence {
+            "zf" | "zo" | "zc" | "za" | "zR" | "zM" => self.fold_command(sequence, visual),
+            "gd" => self.definition(),
+            "gr" => self.references(),
+            "]d" => self.next_diagnostic(true),
+            "[d" => self.next_diagnostic(false),
+            "]n" | "[n" | "gp" => {
+                let target = match sequence {
+                    "gp" => self.syntax.parent_node(self.cursor.y, self.cursor.x),
+                    _ => self.syntax.sibling_node(self.cursor.y, self.cursor.x, sequence == "]n"),
+                };
+
+                if let Some((x, y)) = target {
+                    self.goto(Cursor { x, y });
+                }
+
+                Ok(())
+            },
+            "gc" | "==" | ">>" | "<<" if !self.modifiable() => Ok(()),
+            "gc" => self.comment_command(visual),
+            "==" | ">>" | "<<" => {
+                self.indent_command(sequence, visual);
+                Ok(())
+            },
+            _ => Ok(()),
+        }
+    }
+
     pub fn run(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        self.term.clear_screen()?;
+        let session = Session::start()?;
 
-        let mut command: Vec<char> = Vec::new();
         let mut visual = Visual {
             x: 0,
             y: 0,
@@ -677,24 +1195,102 @@ impl Editor {
         self.screen.height = size.0 as usize;
         self.screen.width = size.1 as usize;
 
-        let mut manager = BufferManager::new(&self);
+        let mut manager = BufferManager::new(self);
 
-        loop {
+        if let Err(err) = self.startup(&mut manager) {
+            self.log(&err.to_string());
+        }
+
+        while !self.quit {
             if self.mode == Mode::Command {
-                self.log(&command.iter().collect::<String>());
+                let text = self.cmdline.text();
+                self.log(&text);
+            }
 
-                self.clamp_cursor();
-                self.render(&manager)?;
+            self.lsp_poll(&mut manager);
+            self.finder_poll();
+            self.quickfix_poll();
 
-                self.term.move_cursor_to(command.len(), self.screen.height - 1)?;
-            } else {
-                self.clamp_cursor();
-                self.render(&manager)?;
+            self.clamp_cursor();
+            self.render(&manager)?;
+
+            let next = self.next_event(&mut manager)?;
+
+            if let Some(sequence) = self.sequence.take() {
+                // a sequence is not a motion for `gc`
+                self.operator = None;
+
+                if let Err(err) = self.run_sequence(sequence, &visual) {
+                    self.log(&err.to_string());
+                }
+                continue;
+            }
+
+            let event = match next {
+                Some(Event::Key(event)) => event,
+                Some(Event::Mouse(_)) if self.finder.is_some() => continue,
+                Some(Event::Mouse(mouse)) => {
+                    self.close_completion();
+                    self.handle_mouse(&mouse, &mut visual, &mut manager)?;
+                    continue;
+                },
+                Some(Event::Paste(text)) => {
+                    self.close_completion();
+
+                    if self.finder.is_some() {
+                        self.finder_paste(&text);
+                    } else if self.mode == Mode::Command {
+                        self.cmdline.insert(&text.chars().map(|character| if character.is_control() { ' ' } else { character }).collect::<String>());
+                    } else if self.modifiable() {
+                        // pasting outside of insert mode inserts the text at the cursor instead of running it as commands
+                        self.insert_text(&text);
+
+                        if self.mode == Mode::Visual {
+                            self.mode = Mode::Normal;
+                        }
+                    }
+                    continue;
+                },
+                Some(Event::Wake) | None => continue,
+            };
+
+            if self.confirm_key(&event) {
+                continue;
             }
 
-            let key = self.term.read_key()?;
+            if self.finder.is_some() {
+                if let Err(err) = self.finder_key(&event, &mut manager) {
+                    self.log(&err.to_string());
+                }
+                continue;
+            }
 
-            match key {
+            if self.mode == Mode::Command && self.command_line_key(&event, &mut manager) {
+                continue;
+            }
+
+            if self.mode == Mode::Normal && self.popup_key(&event, &mut manager) {
+                continue;
+            }
+
+            if self.mode == Mode::Normal && self.operator.is_none() && self.browser_key(&event, &mut manager) {
+                continue;
+            }
+
+            // the line `gc` was typed on, the key after it is the motion
+            let operator = self.operator.take();
+
+            // any other key closes the completion menu
+            let completing = self.mode == Mode::Insert && event.modifiers == Modifiers::CTRL && matches!(event.key, Key::Char('n') | Key::Char('p'));
+            if !completing {
+                self.close_completion();
+            }
+
+            match event.key {
+                Key::ArrowUp | Key::ArrowDown | Key::ArrowLeft | Key::ArrowRight
+                    if event.modifiers != Modifiers::NONE => {
+                    self.handle_modifier(&event, &mut manager)?;
+                },
                 Key::ArrowUp => {
                     self.move_cursor(Direction::Up);
                 },
@@ -709,47 +1305,60 @@ impl Editor {
                 },
                 Key::Enter => {
                     if self.mode == Mode::Insert {
-                        self.newline(true);
-                    } else if self.mode == Mode::Command {
-                        if let Err(err) = self.command(command.iter().collect::<String>(), &mut manager) {
+                        if !self.open_block() {
+                            self.newline(true);
+                        }
+                    } else if self.mode == Mode::Normal && self.list.is_some() {
+                        if let Err(err) = self.open_location(&mut manager) {
                             self.log(&err.to_string());
                         }
-                        self.mode = Mode::Normal;
-                        command = Vec::new();
                     }
                 },
-                Key::Backspace => {
-                    if self.mode == Mode::Insert {
-                        self.remove();
-                    } else if self.mode == Mode::Command {
-                        command.pop();
-                    }
+                Key::Backspace if self.mode == Mode::Insert => {
+                    self.remove();
                 },
-                Key::Tab => {
-                    if self.mode == Mode::Insert {
-                        for _ in 0..TAB_SIZE {
-                            self.buffer[self.cursor.y].insert(self.cursor.x, ' ');
-                            self.move_cursor(Direction::Right);
-                        }
-                        self.refresh = true;
+                Key::Tab if self.mode == Mode::Insert => {
+                    for _ in 0..self.options.get_int("tabsize") {
+                        self.buffer[self.cursor.y].insert(self.cursor.x, ' ');
+                        self.move_cursor(Direction::Right);
                     }
+                    self.changed(self.cursor.y, 1, 1);
                 },
                 Key::Escape => {
-                    command = Vec::new();
-
                     self.mode = Mode::Normal;
                     self.refresh = true;
                 },
-                Key::UnknownEscSeq(_) => {
-                    self.handle_escape(&mut manager)?;
+                Key::Char(character) if completing => {
+                    self.complete(character == 'n', &manager);
                 },
+                Key::Char(' ') if event.modifiers == Modifiers::CTRL && self.mode == Mode::Insert => {
+                    if let Err(err) = self.lsp_complete() {
+                        self.log(&err.to_string());
+                    }
+                },
+                Key::Char('p') if event.modifiers == Modifiers::CTRL && self.mode == Mode::Normal => {
+                    self.open_finder(Source::Files, &manager);
+                },
+                Key::Char('z') if event.modifiers == Modifiers::CTRL => {
+                    session.suspend()?;
+                    self.renderer.invalidate();
+
+                    let size = self.term.size();
+                    self.screen.height = size.0 as usize;
+                    self.screen.width = size.1 as usize;
+                    self.refresh = true;
+                },
+                Key::Char(_) if event.modifiers.ctrl || event.modifiers.alt || event.modifiers.meta => {},
                 Key::Char(character) => {
                     if self.mode == Mode::Insert {
                         /* -- INSERT -- */
-                        self.insert(character);
-                    } else if self.mode == Mode::Command {
-                        /* -- COMMAND -- */
-                        command.push(character);
+                        self.type_character(character);
+                        self.indent_closing();
+                    } else if self.mode == Mode::Visual && ['=', '>', '<'].contains(&character) {
+                        /* -- VISUAL INDENT -- */
+                        if self.modifiable() {
+                            self.indent_command(&character.to_string(), &visual);
+                        }
                     } else if self.mode == Mode::Visual {
                         /* -- VISUAL -- */
                         match character {
@@ -759,9 +1368,20 @@ impl Editor {
                                 self.mode = Mode::Normal;
                                 self.refresh = true;
                             },
+                            ':' => {
+                                // the selected lines are the range of the command
+                                self.marks.insert('<', visual.y.min(self.cursor.y));
+                                self.marks.insert('>', visual.y.max(self.cursor.y));
+
+                                self.open_command_line();
+                                self.cmdline.insert("'<,'>");
+                            },
                             'd' => {
                                 self.copy(&visual);
-                                self.delete(&visual);
+
+                                if self.modifiable() {
+                                    self.delete(&visual);
+                                }
 
                                 self.mode = Mode::Normal;
                                 self.refresh = true;
@@ -772,21 +1392,22 @@ impl Editor {
                         /* -- NORMAL -- */
                         match character {
                             'v' | 'V' => {
-                                visual = Visual::new(&self.cursor, if character == 'v' { false } else { true });
+                                visual = Visual::new(&self.cursor, character != 'v');
                                 self.mode = Mode::Visual;
                             },
                             'd' | 'y' => {
                                 visual = Visual::new(&self.cursor, true);
                                 self.mode = Mode::Visual;
                             },
-                            'p' => {
+                            'p' if self.modifiable() => {
                                 self.paste(&visual);
                             },
-                            'o' => {
+                            'o' if self.modifiable() => {
                                 self.newline(false);
                                 self.mode = Mode::Insert;
                             },
-                            'i' => {
+                            'i' if self.modifiable() => {
+                                self.folds.reveal(self.cursor.y);
                                 self.mode = Mode::Insert;
                             },
                             'n' => {
@@ -795,9 +1416,16 @@ impl Editor {
                             'b' => {
                                 self.previous_match();
                             },
+                            '%' => {
+                                self.jump_bracket();
+                            },
+                            'K' => {
+                                if let Err(err) = self.hover() {
+                                    self.log(&err.to_string());
+                                }
+                            },
                             ':' => {
-                                command.push(character);
-                                self.mode = Mode::Command;
+                                self.open_command_line();
                             },
                             _ => {},
                         }
@@ -806,7 +1434,18 @@ impl Editor {
                 },
                 _ => {},
             }
+
+            // `gcc` comments the cursor line since `c` does not move
+            if let Some(start) = operator {
+                if self.mode == Mode::Normal && event.key != Key::Escape {
+                    if let Err(err) = self.toggle_comment(start.min(self.cursor.y), start.max(self.cursor.y)) {
+                        self.log(&err.to_string());
+                    }
+                }
+            }
         }
+
+        Ok(())
     }
 }
 
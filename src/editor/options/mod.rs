@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(usize),
+    Str(String),
+}
+
+impl Value {
+    fn as_string(&self) -> String {
+        match self {
+            Value::Bool(value) => value.to_string(),
+            Value::Int(value) => value.to_string(),
+            Value::Str(value) => value.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scope {
+    Global,
+    Buffer,
+    Window,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Kind {
+    Bool,
+    Int(usize, usize),
+    Str,
+    Enum(&'static [&'static str]),
+    // a comma separated list of the values
+    Flags(&'static [&'static str]),
+}
+
+struct Definition {
+    name: &'static str,
+    short: &'static str,
+    scope: Scope,
+    kind: Kind,
+    default: Value,
+}
+
+fn definitions() -> Vec<Definition> {
+    vec![
+        Definition {
+            name: "tabsize",
+            short: "ts",
+            scope: Scope::Buffer,
+            kind: Kind::Int(1, 16),
+            default: Value::Int(4),
+        },
+        Definition {
+            name: "shiftwidth",
+            short: "sw",
+            scope: Scope::Buffer,
+            kind: Kind::Int(0, 16),
+            default: Value::Int(0),
+        },
+        Definition {
+            name: "fileformat",
+            short: "ff",
+            scope: Scope::Buffer,
+            kind: Kind::Enum(&["unix", "dos"]),
+            default: Value::Str(String::from("unix")),
+        },
+        Definition {
+            name: "paragraph",
+            short: "para",
+            scope: Scope::Window,
+            kind: Kind::Int(1, 1000),
+            default: Value::Int(47),
+        },
+        Definition {
+            name: "fillchar",
+            short: "fc",
+            scope: Scope::Window,
+            kind: Kind::Str,
+            default: Value::Str(String::from("~")),
+        },
+        Definition {
+            name: "timeoutlen",
+            short: "tm",
+            scope: Scope::Global,
+            kind: Kind::Int(0, 10000),
+            default: Value::Int(1000),
+        },
+        Definition {
+            name: "ttimeoutlen",
+            short: "ttm",
+            scope: Scope::Global,
+            kind: Kind::Int(0, 1000),
+            default: Value::Int(50),
+        },
+        Definition {
+            name: "mapleader",
+            short: "leader",
+            scope: Scope::Global,
+            kind: Kind::Str,
+            default: Value::Str(String::from("\\")),
+        },
+        Definition {
+            name: "syncoutput",
+            short: "sync",
+            scope: Scope::Global,
+            kind: Kind::Bool,
+            default: Value::Bool(true),
+        },
+        Definition {
+            name: "laststatus",
+            short: "ls",
+            scope: Scope::Global,
+            kind: Kind::Bool,
+            default: Value::Bool(true),
+        },
+        Definition {
+            name: "colordepth",
+            short: "cdp",
+            scope: Scope::Global,
+            kind: Kind::Enum(&["auto", "16", "256", "truecolor"]),
+            default: Value::Str(String::from("auto")),
+        },
+        Definition {
+            name: "foldmethod",
+            short: "fdm",
+            scope: Scope::Buffer,
+            kind: Kind::Enum(&["manual", "indent", "syntax"]),
+            default: Value::Str(String::from("manual")),
+        },
+        Definition {
+            name: "autopairs",
+            short: "ap",
+            scope: Scope::Buffer,
+            kind: Kind::Bool,
+            default: Value::Bool(false),
+        },
+        Definition {
+            name: "completeopt",
+            short: "cot",
+            scope: Scope::Global,
+            kind: Kind::Flags(&["menu", "menuone", "noselect", "fuzzy"]),
+            default: Value::Str(String::from("menu,menuone")),
+        },
+        Definition {
+            name: "makeprg",
+            short: "mp",
+            scope: Scope::Global,
+            kind: Kind::Str,
+            default: Value::Str(String::from("cargo build")),
+        },
+        Definition {
+            name: "errorformat",
+            short: "efm",
+            scope: Scope::Global,
+            kind: Kind::Str,
+            default: Value::Str(String::from("rustc,pytest,gcc,eslint")),
+        },
+        Definition {
+            name: "signcolumn",
+            short: "scl",
+            scope: Scope::Window,
+            kind: Kind::Enum(&["auto", "yes", "no"]),
+            default: Value::Str(String::from("auto")),
+        },
+        Definition {
+            name: "virtualtext",
+            short: "vt",
+            scope: Scope::Global,
+            kind: Kind::Bool,
+            default: Value::Bool(false),
+        },
+        Definition {
+            name: "dirsort",
+            short: "ds",
+            scope: Scope::Global,
+            kind: Kind::Enum(&["name", "time", "size"]),
+            default: Value::Str(String::from("name")),
+        },
+        Definition {
+            name: "dirhidden",
+            short: "dh",
+            scope: Scope::Global,
+            kind: Kind::Bool,
+            default: Value::Bool(false),
+        },
+    ]
+}
+
+pub struct Options {
+    definitions: Vec<Definition>,
+
+    global: HashMap<String, Value>,
+    window: HashMap<String, Value>,
+
+    pub buffer: HashMap<String, Value>,
+}
+
+impl Options {
+    pub fn new() -> Options {
+        let definitions = definitions();
+        let global = definitions.iter()
+            .map(|definition| (definition.name.to_string(), definition.default.clone()))
+            .collect::<HashMap<String, Value>>();
+
+        Options {
+            definitions,
+
+            global,
+            window: HashMap::new(),
+
+            buffer: HashMap::new(),
+        }
+    }
+
+    fn definition(&self, name: &str) -> Result<&Definition, Box<dyn std::error::Error>> {
+        self.definitions.iter()
+            .find(|definition| definition.name == name || definition.short == name)
+            .ok_or_else(|| format!("Unknown option: `{}`", name).into())
+    }
+
+    fn local(&mut self, scope: Scope) -> Option<&mut HashMap<String, Value>> {
+        match scope {
+            Scope::Global => None,
+            Scope::Buffer => Some(&mut self.buffer),
+            Scope::Window => Some(&mut self.window),
+        }
+    }
+
+    // the editor only asks for options it defines, an unknown name is a typo in the code
+    pub fn get(&self, name: &str) -> Value {
+        let definition = self.definition(name);
+        debug_assert!(definition.is_ok(), "Unknown option: `{}`", name);
+
+        let definition = match definition {
+            Ok(definition) => definition,
+            Err(_) => return Value::Bool(false),
+        };
+
+        let local = match definition.scope {
+            Scope::Global => None,
+            Scope::Buffer => self.buffer.get(definition.name),
+            Scope::Window => self.window.get(definition.name),
+        };
+
+        local.or_else(|| self.global.get(definition.name))
+            .cloned()
+            .unwrap_or(Value::Bool(false))
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.definitions.iter().map(|definition| definition.name).collect()
+    }
+
+    pub fn get_bool(&self, name: &str) -> bool {
+        matches!(self.get(name), Value::Bool(true))
+    }
+
+    pub fn get_int(&self, name: &str) -> usize {
+        match self.get(name) {
+            Value::Int(value) => value,
+            _ => 0,
+        }
+    }
+
+    pub fn get_str(&self, name: &str) -> String {
+        match self.get(name) {
+            Value::Str(value) => value,
+            _ => String::new(),
+        }
+    }
+
+    fn parse(kind: Kind, name: &str, value: &str) -> Result<Value, Box<dyn std::error::Error>> {
+        match kind {
+            Kind::Bool => Err(format!("Invalid argument: `{}={}`", name, value).into()),
+            Kind::Int(min, max) => {
+                let value = value.parse::<usize>()
+                    .map_err(|_| format!("Number required: `{}={}`", name, value))?;
+
+                if value < min || value > max {
+                    return Err(format!("Out of range ({}-{}): `{}={}`", min, max, name, value).into());
+                }
+
+                Ok(Value::Int(value))
+            },
+            Kind::Str => Ok(Value::Str(value.to_string())),
+            Kind::Enum(values) => {
+                if !values.contains(&value) {
+                    return Err(format!("Expected one of [{}]: `{}={}`", values.join(", "), name, value).into());
+                }
+
+                Ok(Value::Str(value.to_string()))
+            },
+            Kind::Flags(values) => {
+                if let Some(flag) = value.split(',').find(|flag| !flag.is_empty() && !values.contains(flag)) {
+                    return Err(format!("Expected any of [{}]: `{}={}`", values.join(", "), name, flag).into());
+                }
+
+                Ok(Value::Str(value.to_string()))
+            },
+        }
+    }
+
+    fn assign(&mut self, name: &str, value: Value, local: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let definition = self.definition(name)?;
+        let (name, scope) = (definition.name.to_string(), definition.scope);
+
+        match self.local(scope) {
+            Some(map) => {
+                map.insert(name.clone(), value.clone());
+            },
+            None if local => {
+                return Err(format!("Option is global: `{}`", name).into());
+            },
+            None => {},
+        }
+
+        if !local {
+            self.global.insert(name, value);
+        }
+
+        Ok(())
+    }
+
+    fn show(&self, name: &str) -> Result<String, Box<dyn std::error::Error>> {
+        let definition = self.definition(name)?;
+
+        Ok(match self.get(definition.name) {
+            Value::Bool(true) => definition.name.to_string(),
+            Value::Bool(false) => format!("no{}", definition.name),
+            value => format!("{}={}", definition.name, value.as_string()),
+        })
+    }
+
+    fn set_argument(&mut self, argument: &str, local: bool) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        if let Some(name) = argument.strip_suffix('?') {
+            return Ok(Some(self.show(name)?));
+        }
+
+        if let Some((name, value)) = argument.split_once('=') {
+            let definition = self.definition(name)?;
+            let (name, value) = (definition.name, Self::parse(definition.kind, definition.name, value)?);
+
+            self.assign(name, value, local)?;
+            return Ok(None);
+        }
+
+        if let Some(name) = argument.strip_suffix('!') {
+            let definition = self.definition(name)?;
+
+            if let Kind::Bool = definition.kind {
+                let name = definition.name;
+                let value = !self.get_bool(name);
+
+                self.assign(name, Value::Bool(value), local)?;
+                return Ok(None);
+            }
+
+            return Err(format!("Invalid argument: `{}`", argument).into());
+        }
+
+        match self.definition(argument) {
+            Ok(definition) => {
+                if let Kind::Bool = definition.kind {
+                    self.assign(definition.name, Value::Bool(true), local)?;
+                    Ok(None)
+                } else {
+                    Ok(Some(self.show(argument)?))
+                }
+            },
+            Err(err) => {
+                let name = argument.strip_prefix("no").ok_or(err)?;
+                let definition = self.definition(name)?;
+
+                if let Kind::Bool = definition.kind {
+                    self.assign(definition.name, Value::Bool(false), local)?;
+                    Ok(None)
+                } else {
+                    Err(format!("Invalid argument: `{}`", argument).into())
+                }
+            },
+        }
+    }
+
+    pub fn set(&mut self, arguments: &str, local: bool) -> Result<String, Box<dyn std::error::Error>> {
+        if arguments.trim().is_empty() {
+            return Ok(self.changed());
+        }
+
+        let mut output: Vec<String> = Vec::new();
+
+        for argument in arguments.split_whitespace() {
+            if let Some(message) = self.set_argument(argument, local)? {
+                output.push(message);
+            }
+        }
+
+        Ok(output.join(" "))
+    }
+
+    fn changed(&self) -> String {
+        self.definitions.iter()
+            .filter(|definition| self.get(definition.name) != definition.default)
+            .filter_map(|definition| self.show(definition.name).ok())
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
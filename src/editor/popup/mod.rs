@@ -0,0 +1,152 @@
+use crate::editor::{Editor, Cursor};
+use crate::editor::buffermanager::BufferManager;
+use crate::editor::input::{Key, KeyEvent};
+use crate::editor::render::Frame;
+
+use serde_json::Value;
+
+const MAX_POPUP_HEIGHT: usize = 12;
+
+// what enter does with the selected line
+#[derive(Clone, Debug)]
+pub enum Pick {
+    Nothing,
+    Locations(Vec<(String, Cursor)>),
+    Actions(String, Vec<Value>),
+}
+
+#[derive(Clone, Debug)]
+pub struct Popup {
+    lines: Vec<String>,
+    first: usize,
+    selected: Option<usize>,
+    pick: Pick,
+}
+
+impl Popup {
+    // a popup that only shows text
+    pub fn text(text: &str) -> Popup {
+        Popup {
+            lines: text.lines().map(String::from).collect(),
+            first: 0,
+            selected: None,
+            pick: Pick::Nothing,
+        }
+    }
+
+    pub fn list(lines: Vec<String>, pick: Pick) -> Popup {
+        Popup {
+            lines,
+            first: 0,
+            selected: Some(0),
+            pick,
+        }
+    }
+}
+
+impl Editor {
+    pub fn open_popup(&mut self, popup: Popup) {
+        self.popup = Some(popup);
+        self.refresh = true;
+    }
+
+    pub fn close_popup(&mut self) {
+        if self.popup.take().is_some() {
+            self.refresh = true;
+        }
+    }
+
+    // up and down move through the popup and enter picks a line, other keys close it and are handled as usual
+    pub fn popup_key(&mut self, event: &KeyEvent, manager: &mut BufferManager) -> bool {
+        let popup = match &mut self.popup {
+            Some(popup) => popup,
+            None => return false,
+        };
+
+        let height = popup.lines.len().min(MAX_POPUP_HEIGHT);
+        let last = popup.lines.len().saturating_sub(1);
+
+        match (&event.key, popup.selected) {
+            (Key::ArrowDown, Some(selected)) => popup.selected = Some((selected + 1).min(last)),
+            (Key::ArrowUp, Some(selected)) => popup.selected = Some(selected.saturating_sub(1)),
+            (Key::ArrowDown, None) => popup.first = (popup.first + 1).min(popup.lines.len().saturating_sub(height)),
+            (Key::ArrowUp, None) => popup.first = popup.first.saturating_sub(1),
+            (Key::Enter, Some(selected)) => {
+                let pick = popup.pick.clone();
+                self.close_popup();
+
+                if let Err(err) = self.pick(pick, selected, manager) {
+                    self.log(&err.to_string());
+                }
+                return true;
+            },
+            _ => {
+                self.close_popup();
+                return false;
+            },
+        }
+
+        // the selected line stays in view
+        if let Some(selected) = popup.selected {
+            if selected < popup.first {
+                popup.first = selected;
+            } else if selected >= popup.first + height {
+                popup.first = selected + 1 - height;
+            }
+        }
+
+        self.refresh = true;
+        true
+    }
+
+    fn pick(&mut self, pick: Pick, selected: usize, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        match pick {
+            Pick::Nothing => {},
+            Pick::Locations(locations) => {
+                if let Some((filename, cursor)) = locations.get(selected) {
+                    manager.open(self, filename)?;
+                    self.goto(*cursor);
+                }
+            },
+            Pick::Actions(server, actions) => {
+                if let Some(action) = actions.get(selected) {
+                    self.run_code_action(&server, action, manager)?;
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    // drawn like the completion menu, under the cursor line or over it when there is no room
+    pub fn render_popup(&self, frame: &mut Frame) {
+        let popup = match &self.popup {
+            Some(popup) => popup,
+            None => return,
+        };
+
+        let lines = self.screen.height - self.bottom_bar();
+        let height = popup.lines.len().min(MAX_POPUP_HEIGHT).min(lines.saturating_sub(1));
+        let row = self.cursor_row();
+
+        let top = if row + 1 + height <= lines {
+            row + 1
+        } else {
+            row.saturating_sub(height)
+        };
+
+        let width = popup.lines.iter().map(|line| line.chars().count()).max().unwrap_or(0) + 2;
+        let width = width.min(self.screen.width);
+        let x = (self.gutter() + self.cursor.x.saturating_sub(self.screen.x)).min(self.screen.width - width);
+
+        let normal = self.theme.style(if self.theme.has("menu") { "menu" } else { "bar" });
+        let selected = self.theme.style(if self.theme.has("menu_selected") { "menu_selected" } else { "mode" });
+
+        for (index, line) in popup.lines.iter().enumerate().skip(popup.first).take(height) {
+            let style = if popup.selected == Some(index) { &selected } else { &normal };
+            let text = format!(" {:<width$}", line, width = width - 1).chars().take(width).collect::<String>();
+
+            frame.put(x, top + index - popup.first, &text, style);
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use crate::editor::{Editor, Cursor, config_path};
+use crate::editor::buffermanager::BufferManager;
+use crate::editor::signs::Sign;
+use crate::editor::list::List;
+
+use regex::Regex;
+use serde_json::Value;
+
+use std::collections::HashMap;
+use std::fs;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+// the lines of an error format, a line with a `line` group adds an entry and the groups of
+// other lines are remembered for the entries after them, like the file eslint prints first
+const FORMATS: [(&str, &[&str]); 4] = [
+    ("rustc", &[
+        r"^(?P<kind>error|warning|note|help)(?:\[\w+\])?: (?P<message>.+)$",
+        r"^\s*--> (?P<file>[^:]+):(?P<line>\d+):(?P<column>\d+)$",
+    ]),
+    ("pytest", &[
+        r"^E\s+(?P<message>.+)$",
+        r"^(?P<file>\S+\.py):(?P<line>\d+): \w+$",
+    ]),
+    ("gcc", &[
+        r"^(?P<file>[^:\s]+):(?P<line>\d+):(?P<column>\d+): (?:fatal )?(?P<kind>error|warning|note): (?P<message>.+)$",
+        r"^(?P<file>[^:\s]+):(?P<line>\d+):(?:(?P<column>\d+):)? (?P<message>.+)$",
+    ]),
+    ("eslint", &[
+        r"^(?P<file>\S+\.(?:js|jsx|ts|tsx|mjs|cjs|vue))$",
+        r"^\s+(?P<line>\d+):(?P<column>\d+)\s+(?P<kind>error|warning)\s+(?P<message>.+?)\s*$",
+    ]),
+];
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    filename: String,
+    cursor: Cursor,
+    kind: String,
+    message: String,
+}
+
+// the entries of a finished :make and whether it succeeded
+type Made = Result<(Vec<Entry>, bool), String>;
+
+#[derive(Debug)]
+pub struct Quickfix {
+    entries: Vec<Entry>,
+    index: Option<usize>,
+    // the command of a running :make
+    running: Option<(String, Receiver<Made>)>,
+}
+
+impl Quickfix {
+    pub fn new() -> Quickfix {
+        Quickfix {
+            entries: Vec::new(),
+            index: None,
+            running: None,
+        }
+    }
+}
+
+// the formats named in "errorformat", "errorformats.json" adds formats or replaces the built in ones
+fn formats(names: &str) -> Result<Vec<Vec<Regex>>, Box<dyn std::error::Error>> {
+    let mut known: HashMap<String, Vec<String>> = FORMATS.iter()
+        .map(|(name, lines)| (name.to_string(), lines.iter().map(|line| line.to_string()).collect()))
+        .collect();
+
+    let custom = config_path("errorformats.json").ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|text| serde_json::from_str::<Value>(&text))
+        .transpose()?;
+
+    for (name, lines) in custom.as_ref().and_then(|custom| custom.as_object()).into_iter().flatten() {
+        let lines = lines.as_array().ok_or(format!("The error format `{}` is not a list", name))?;
+        known.insert(name.clone(), lines.iter().filter_map(|line| line.as_str()).map(String::from).collect());
+    }
+
+    names.split(',')
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            let lines = known.get(name).ok_or(format!("Unknown error format: {}", name))?;
+
+            lines.iter()
+                .map(|line| Regex::new(line).map_err(|err| format!("`{}`: {}", name, err).into()))
+                .collect()
+        })
+        .collect()
+}
+
+// every line goes to the first format that matches it
+fn parse(output: &str, formats: &[Vec<Regex>]) -> Vec<Entry> {
+    let mut remembered: Vec<HashMap<&str, String>> = vec![HashMap::new(); formats.len()];
+    let mut entries = Vec::new();
+
+    for line in output.lines() {
+        let found = formats.iter().enumerate()
+            .find_map(|(index, lines)| lines.iter().find_map(|regex| regex.captures(line)).map(|captures| (index, captures)));
+
+        let (index, captures) = match found {
+            Some(found) => found,
+            None => continue,
+        };
+
+        let mut fields = remembered[index].clone();
+        for name in ["file", "line", "column", "kind", "message"] {
+            if let Some(value) = captures.name(name) {
+                fields.insert(name, value.as_str().to_string());
+            }
+        }
+
+        let line = match captures.name("line").and_then(|line| line.as_str().parse::<usize>().ok()) {
+            Some(line) => line,
+            None => {
+                remembered[index] = fields;
+                continue;
+            },
+        };
+
+        let column = captures.name("column").and_then(|column| column.as_str().parse::<usize>().ok()).unwrap_or(1);
+
+        if let Some(filename) = fields.get("file") {
+            entries.push(Entry {
+                filename: filename.clone(),
+                cursor: Cursor { x: column.saturating_sub(1), y: line.saturating_sub(1) },
+                kind: fields.get("kind").cloned().unwrap_or_else(|| String::from("error")),
+                message: fields.get("message").cloned().unwrap_or_default(),
+            });
+        }
+    }
+
+    entries
+}
+
+impl Editor {
+    // runs "makeprg" with the arguments on another thread, the quickfix list is filled when it is done
+    pub fn make(&mut self, arguments: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some((command, _)) = &self.quickfix.running {
+            return Err(format!("`{}` is still running", command).into());
+        }
+
+        let formats = formats(&self.options.get_str("errorformat"))?;
+        let command = format!("{} {}", self.options.get_str("makeprg"), arguments).trim().to_string();
+
+        let (sender, receiver) = mpsc::channel();
+        let waker = self.input.waker();
+        let shell = command.clone();
+
+        thread::spawn(move || {
+            let result = Command::new("sh")
+                .arg("-c")
+                .arg(&shell)
+                .stdin(Stdio::null())
+                .output()
+                .map(|output| {
+                    let text = String::from_utf8_lossy(&output.stdout).to_string() + &String::from_utf8_lossy(&output.stderr);
+                    (parse(&text, &formats), output.status.success())
+                })
+                .map_err(|err| format!("Failed to run `{}`: {}", shell, err));
+
+            let _ = sender.send(result);
+            let _ = waker.send(Vec::new());
+        });
+
+        self.log(&format!("Running `{}`", command));
+        self.quickfix.running = Some((command, receiver));
+        Ok(())
+    }
+
+    pub fn quickfix_poll(&mut self) {
+        let (command, result) = match &self.quickfix.running {
+            Some((command, receiver)) => match receiver.try_recv() {
+                Ok(result) => (command.clone(), result),
+                Err(TryRecvError::Empty) => return,
+                Err(TryRecvError::Disconnected) => (command.clone(), Err(format!("`{}` stopped", command))),
+            },
+            None => return,
+        };
+
+        self.quickfix.running = None;
+
+        match result {
+            Ok((entries, success)) => {
+                self.set_quickfix(entries);
+
+                let status = if success { "finished" } else { "failed" };
+                self.log(&format!("`{}` {}, {} entries", command, status, self.quickfix.entries.len()));
+            },
+            Err(err) => self.log(&err),
+        }
+    }
+
+    // the entries are marked in the sign column like diagnostics
+    fn set_quickfix(&mut self, entries: Vec<Entry>) {
+        for entry in &self.quickfix.entries {
+            self.signs.clear("quickfix", &entry.filename);
+        }
+
+        let mut files: HashMap<String, Vec<(usize, Sign)>> = HashMap::new();
+
+        for entry in &entries {
+            let (text, group, color) = match entry.kind.as_str() {
+                "error" => ('E', "diagnostic.error", 1),
+                "warning" => ('W', "diagnostic.warning", 3),
+                _ => ('I', "diagnostic.info", 4),
+            };
+
+            files.entry(entry.filename.clone()).or_default().push((entry.cursor.y, Sign {
+                text,
+                group: group.to_string(),
+                color,
+                priority: 3,
+                message: Some(entry.message.clone()),
+            }));
+        }
+
+        for (filename, signs) in files {
+            self.signs.place("quickfix", &filename, signs);
+        }
+
+        self.quickfix.entries = entries;
+        self.quickfix.index = None;
+        self.refresh = true;
+    }
+
+    // :cn and :cp, the first one jumps to the first entry
+    pub fn next_quickfix(&mut self, forward: bool, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        let last = self.quickfix.entries.len().checked_sub(1).ok_or("The quickfix list is empty")?;
+
+        let index = match (self.quickfix.index, forward) {
+            (None, _) => 0,
+            (Some(index), true) if index == last => return Err("No more items".into()),
+            (Some(index), true) => index + 1,
+            (Some(0), false) => return Err("No more items".into()),
+            (Some(index), false) => index - 1,
+        };
+
+        let entry = self.quickfix.entries[index].clone();
+
+        manager.open(self, &entry.filename)?;
+        self.goto(entry.cursor);
+
+        self.quickfix.index = Some(index);
+        self.log(&format!("({} of {}) {}: {}", index + 1, last + 1, entry.kind, entry.message));
+        Ok(())
+    }
+
+    pub fn open_quickfix(&mut self, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        if self.quickfix.entries.is_empty() {
+            return Err("The quickfix list is empty".into());
+        }
+
+        let lines = self.quickfix.entries.iter()
+            .map(|entry| format!("{}:{}:{}: {}: {}", entry.filename, entry.cursor.y + 1, entry.cursor.x + 1, entry.kind, entry.message))
+            .collect();
+
+        let locations = self.quickfix.entries.iter()
+            .map(|entry| (entry.filename.clone(), entry.cursor))
+            .collect();
+
+        manager.open_list(self, "[Quickfix]", List::new(lines, locations))
+    }
+}
@@ -0,0 +1,312 @@
+use crate::editor::theme::{Style, Depth};
+
+use std::io::{self, Write};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cell {
+    character: char,
+    style: usize,
+}
+
+// a virtual screen of styled cells, styles are stored once and referenced by index
+#[derive(Clone, Debug)]
+pub struct Frame {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+    styles: Vec<Style>,
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize) -> Frame {
+        Frame {
+            width,
+            height,
+            cells: vec![Cell { character: ' ', style: 0 }; width * height],
+            styles: vec![Style::new()],
+        }
+    }
+
+    fn style_index(&mut self, style: &Style) -> usize {
+        match self.styles.iter().position(|known| known == style) {
+            Some(index) => index,
+            None => {
+                self.styles.push(style.clone());
+                self.styles.len() - 1
+            },
+        }
+    }
+
+    // writes `text` at the position and returns the column after it, text past the edge is cut off
+    pub fn put(&mut self, x: usize, y: usize, text: &str, style: &Style) -> usize {
+        if y >= self.height {
+            return x;
+        }
+
+        let style = self.style_index(style);
+        let mut x = x;
+
+        for character in text.chars() {
+            if x >= self.width {
+                break;
+            }
+
+            self.cells[y * self.width + x] = Cell {
+                character,
+                style,
+            };
+            x += 1;
+        }
+
+        x
+    }
+
+    // fills the rest of the row with spaces
+    pub fn fill(&mut self, x: usize, y: usize, style: &Style) {
+        let padding = " ".repeat(self.width.saturating_sub(x));
+        self.put(x, y, &padding, style);
+    }
+
+    pub fn copy_row(&mut self, other: &Frame, y: usize) {
+        if other.width != self.width || y >= self.height || y >= other.height {
+            return;
+        }
+
+        for x in 0..self.width {
+            let cell = other.cells[y * other.width + x];
+            let style = self.style_index(&other.styles[cell.style]);
+
+            self.cells[y * self.width + x] = Cell {
+                character: cell.character,
+                style,
+            };
+        }
+    }
+
+    pub fn style_at(&self, x: usize, y: usize) -> Style {
+        self.cells.get(y * self.width + x).map(|cell| self.styles[cell.style].clone()).unwrap_or_default()
+    }
+
+    fn cell(&self, x: usize, y: usize) -> (char, &Style) {
+        let cell = &self.cells[y * self.width + x];
+        (cell.character, &self.styles[cell.style])
+    }
+}
+
+pub struct Renderer {
+    pub previous: Option<Frame>,
+    depth: Depth,
+
+    frames: usize,
+    total: usize,
+    last: usize,
+}
+
+impl Renderer {
+    pub fn new() -> Renderer {
+        Renderer {
+            previous: None,
+            depth: Depth::Ansi,
+
+            frames: 0,
+            total: 0,
+            last: 0,
+        }
+    }
+
+    // forgets what is on the terminal so the next frame is drawn in full
+    pub fn invalidate(&mut self) {
+        self.previous = None;
+    }
+
+    fn move_to(output: &mut String, position: Option<(usize, usize)>, x: usize, y: usize) {
+        match position {
+            Some((current_x, current_y)) if current_y == y && current_x == x => {},
+            Some((current_x, current_y)) if current_y == y && current_x < x => {
+                *output += &format!("\x1b[{}C", x - current_x);
+            },
+            _ => {
+                *output += &format!("\x1b[{};{}H", y + 1, x + 1);
+            },
+        }
+    }
+
+    // only the runs of cells that changed since the last frame are written
+    fn diff(&self, frame: &Frame, depth: Depth) -> String {
+        // styles are encoded for a color depth, cells from another depth are written again
+        let previous = self.previous.as_ref().filter(|previous| previous.width == frame.width && previous.height == frame.height && self.depth == depth);
+
+        let mut output = String::new();
+        let mut position: Option<(usize, usize)> = None;
+
+        if previous.is_none() {
+            output += "\x1b[0m\x1b[2J";
+        }
+
+        for y in 0..frame.height {
+            let mut x = 0;
+
+            while x < frame.width {
+                let (character, style) = frame.cell(x, y);
+
+                if previous.map(|previous| previous.cell(x, y) == (character, style)).unwrap_or(false) {
+                    x += 1;
+                    continue;
+                }
+
+                let start = x;
+                let mut run = String::new();
+
+                while x < frame.width {
+                    let (next, next_style) = frame.cell(x, y);
+
+                    if next_style != style || previous.map(|previous| previous.cell(x, y) == (next, next_style)).unwrap_or(false) {
+                        break;
+                    }
+
+                    run.push(next);
+                    x += 1;
+                }
+
+                Self::move_to(&mut output, position, start, y);
+                output += &style.sgr(depth);
+                output += &run;
+
+                position = if x < frame.width { Some((x, y)) } else { None };
+            }
+        }
+
+        output
+    }
+
+    pub fn draw(&mut self, frame: Frame, cursor: (usize, usize), depth: Depth, synchronized: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let output = self.encode(frame, cursor, depth, synchronized);
+
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(output.as_bytes())?;
+        stdout.flush()?;
+        Ok(())
+    }
+
+    // what has to be written to get from the last frame to this one
+    fn encode(&mut self, frame: Frame, cursor: (usize, usize), depth: Depth, synchronized: bool) -> String {
+        let mut output = String::new();
+
+        // terminals that support synchronized output show the whole frame at once
+        if synchronized {
+            output += "\x1b[?2026h";
+        }
+
+        output += "\x1b[?25l";
+        output += &self.diff(&frame, depth);
+        output += &format!("\x1b[{};{}H\x1b[?25h", cursor.1 + 1, cursor.0 + 1);
+
+        if synchronized {
+            output += "\x1b[?2026l";
+        }
+
+        self.frames += 1;
+        self.total += output.len();
+        self.last = output.len();
+
+        self.previous = Some(frame);
+        self.depth = depth;
+        output
+    }
+
+    pub fn stats(&self) -> String {
+        format!("last frame: {} bytes, average: {} bytes over {} frames", self.last, self.total / self.frames.max(1), self.frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HIDE: &str = "\x1b[?25l";
+    const SHOW: &str = "\x1b[?25h";
+
+    fn screen(lines: &[&str]) -> Frame {
+        let mut frame = Frame::new(80, 24);
+        let style = Style::new();
+
+        for (y, line) in lines.iter().enumerate() {
+            let x = frame.put(0, y, line, &style);
+            frame.fill(x, y, &style);
+        }
+
+        frame
+    }
+
+    // the bytes written for every frame after the first one, like one frame per keystroke
+    fn bytes(frames: &[Frame], cursors: &[(usize, usize)]) -> Vec<usize> {
+        let mut renderer = Renderer::new();
+
+        frames.iter().zip(cursors)
+            .map(|(frame, cursor)| renderer.encode(frame.clone(), *cursor, Depth::Ansi, false).len())
+            .skip(1)
+            .collect()
+    }
+
+    #[test]
+    fn first_frame_is_drawn_in_full() {
+        let mut renderer = Renderer::new();
+        let output = renderer.encode(screen(&["ab"]), (0, 0), Depth::Ansi, false);
+
+        let rows = (0..24).map(|y| format!("\x1b[{};1H\x1b[0m{:<80}", y + 1, if y == 0 { "ab" } else { "" })).collect::<String>();
+
+        assert_eq!(output, format!("{}\x1b[0m\x1b[2J{}\x1b[1;1H{}", HIDE, rows, SHOW));
+        assert_eq!(output.len(), 2201);
+    }
+
+    #[test]
+    fn unchanged_frame_only_moves_the_cursor() {
+        let frames = [screen(&["hello"]), screen(&["hello"]), screen(&["hello"])];
+        let cursors = [(0, 0), (1, 0), (4, 0)];
+
+        assert_eq!(bytes(&frames, &cursors), [format!("{}\x1b[1;2H{}", HIDE, SHOW).len(), format!("{}\x1b[1;5H{}", HIDE, SHOW).len()]);
+    }
+
+    #[test]
+    fn typing_only_writes_the_typed_cells() {
+        let frames = [screen(&["fn"]), screen(&["fn "]), screen(&["fn m"]), screen(&["fn ma"]), screen(&["fn mai"]), screen(&["fn main"])];
+        let cursors = [(2, 0), (3, 0), (4, 0), (5, 0), (6, 0), (7, 0)];
+
+        // the space after "fn" was a space before too, nothing but the cursor changes
+        let mut expected = vec![format!("{}\x1b[1;4H{}", HIDE, SHOW).len()];
+
+        for x in 3..7 {
+            expected.push(format!("{}\x1b[1;{}H\x1b[0mx\x1b[1;{}H{}", HIDE, x + 1, x + 2, SHOW).len());
+        }
+
+        assert_eq!(bytes(&frames, &cursors), expected);
+        assert!(expected.iter().all(|bytes| *bytes < 40));
+    }
+
+    #[test]
+    fn changed_runs_share_a_row() {
+        let mut renderer = Renderer::new();
+        renderer.encode(screen(&["one two three"]), (0, 0), Depth::Ansi, false);
+
+        let output = renderer.encode(screen(&["One two Three"]), (0, 0), Depth::Ansi, false);
+        assert_eq!(output, format!("{}\x1b[1;1H\x1b[0mO\x1b[7C\x1b[0mT\x1b[1;1H{}", HIDE, SHOW));
+    }
+
+    #[test]
+    fn synchronized_output_wraps_the_frame() {
+        let mut renderer = Renderer::new();
+        renderer.encode(screen(&[""]), (0, 0), Depth::Ansi, true);
+
+        let output = renderer.encode(screen(&[""]), (0, 0), Depth::Ansi, true);
+        assert_eq!(output, format!("\x1b[?2026h{}\x1b[1;1H{}\x1b[?2026l", HIDE, SHOW));
+    }
+
+    #[test]
+    fn another_depth_redraws_everything() {
+        let mut renderer = Renderer::new();
+        renderer.encode(screen(&["x"]), (0, 0), Depth::Ansi, false);
+
+        let output = renderer.encode(screen(&["x"]), (0, 0), Depth::TrueColor, false);
+        assert!(output.contains("\x1b[2J"));
+    }
+}
@@ -0,0 +1,104 @@
+use crate::editor::*;
+
+use sha2::{Digest, Sha256};
+
+use std::path::Path;
+use std::fs;
+
+const PROJECT_SCRIPT: &str = ".te";
+
+// the hash has to stay the same across builds and must not be forged by a crafted file
+fn checksum(content: &str) -> String {
+    Sha256::digest(content.as_bytes()).iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn project_script() -> Result<(String, String), Box<dyn std::error::Error>> {
+    let path = fs::canonicalize(PROJECT_SCRIPT)?.to_string_lossy().to_string();
+    let content = fs::read_to_string(&path)?;
+
+    Ok((path, content))
+}
+
+fn is_trusted(path: &str, content: &str) -> Result<bool, Box<dyn std::error::Error>> {
+    let trusted = fs::read_to_string(config_path("trusted")?).unwrap_or_default();
+    let entry = format!("{} {}", checksum(content), path);
+
+    Ok(trusted.lines().any(|line| line == entry))
+}
+
+impl Editor {
+    // a bad line does not stop the rest of the script, the first error is reported
+    fn execute_script(&mut self, path: &str, content: &str, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        let mut first = None;
+
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('"') || line.starts_with('#') {
+                continue;
+            }
+
+            let cmd = if line.starts_with(':') { line.to_string() } else { format!(":{}", line) };
+
+            if let Err(err) = self.command(cmd, manager) {
+                first.get_or_insert(format!("{}:{}: {}", path, index + 1, err));
+            }
+        }
+
+        match first {
+            Some(err) => Err(err.into()),
+            None => Ok(()),
+        }
+    }
+
+    pub fn source(&mut self, path: &str, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+
+        self.execute_script(path, &content, manager)
+    }
+
+    pub fn trust(&mut self, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        let (path, content) = project_script().map_err(|_| format!("No `{}` in the working directory", PROJECT_SCRIPT))?;
+
+        if !is_trusted(&path, &content)? {
+            // replace any entry left over from an older version of the file
+            let mut trusted = fs::read_to_string(config_path("trusted")?)
+                .unwrap_or_default()
+                .lines()
+                .filter(|line| !line.ends_with(&format!(" {}", path)))
+                .map(|line| line.to_string() + "\n")
+                .collect::<String>();
+
+            trusted += &format!("{} {}\n", checksum(&content), path);
+
+            write_config("trusted", &trusted)?;
+        }
+
+        self.execute_script(&path, &content, manager)
+    }
+
+    pub fn startup(&mut self, manager: &mut BufferManager) -> Result<(), Box<dyn std::error::Error>> {
+        // without a home directory there is no init file, the project one can still run
+        if let Ok(init) = config_path("init.te") {
+            if Path::new(&init).exists() {
+                if let Err(err) = self.source(&init, manager) {
+                    self.log(&err.to_string());
+                }
+            }
+        }
+
+        if !Path::new(PROJECT_SCRIPT).exists() {
+            return Ok(());
+        }
+
+        let (path, content) = project_script()?;
+
+        if is_trusted(&path, &content)? {
+            self.execute_script(&path, &content, manager)?;
+        } else {
+            self.log(&format!("`{}` is not trusted, run `:trust` to allow it", path));
+        }
+
+        Ok(())
+    }
+}
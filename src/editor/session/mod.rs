@@ -0,0 +1,112 @@
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::io::{self, Write};
+use std::panic;
+
+// alternate screen, kitty keyboard protocol, SGR mouse reports and bracketed paste,
+// terminals without support for one of them ignore it
+const ENTER: &[u8] = b"\x1b[?1049h\x1b[>1u\x1b[?1000h\x1b[?1002h\x1b[?1006h\x1b[?2004h";
+const LEAVE: &[u8] = b"\x1b[?2004l\x1b[?1006l\x1b[?1002l\x1b[?1000l\x1b[<u\x1b[0m\x1b[?25h\x1b[?1049l";
+
+static ORIGINAL: OnceLock<libc::termios> = OnceLock::new();
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+fn write_all(bytes: &[u8]) {
+    // only uses `write` so it is safe to call from a signal handler
+    let mut written = 0;
+
+    while written < bytes.len() {
+        let length = unsafe { libc::write(libc::STDOUT_FILENO, bytes[written..].as_ptr() as *const libc::c_void, bytes.len() - written) };
+
+        if length <= 0 {
+            break;
+        }
+        written += length as usize;
+    }
+}
+
+fn raw_mode() -> io::Result<()> {
+    let mut termios = unsafe { std::mem::zeroed::<libc::termios>() };
+
+    if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let original = *ORIGINAL.get_or_init(|| termios);
+
+    // output processing is kept so newlines still return the cursor to the first column
+    unsafe { libc::cfmakeraw(&mut termios) };
+    termios.c_oflag = original.c_oflag;
+
+    if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &termios) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+fn enter() -> io::Result<()> {
+    raw_mode()?;
+    write_all(ENTER);
+
+    ACTIVE.store(true, Ordering::SeqCst);
+    Ok(())
+}
+
+fn leave() {
+    // a panic restores the terminal before unwinding drops the session, the second call does nothing
+    if !ACTIVE.swap(false, Ordering::SeqCst) {
+        return;
+    }
+
+    write_all(LEAVE);
+
+    if let Some(original) = ORIGINAL.get() {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, original) };
+    }
+}
+
+extern "C" fn handle_signal(signal: libc::c_int) {
+    leave();
+    unsafe { libc::_exit(128 + signal) };
+}
+
+// puts the terminal into the state the editor needs and restores it when dropped,
+// on a panic and when the editor is terminated by a signal
+pub struct Session;
+
+impl Session {
+    pub fn start() -> Result<Session, Box<dyn std::error::Error>> {
+        enter()?;
+
+        let hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            leave();
+            hook(info);
+        }));
+
+        for signal in [libc::SIGTERM, libc::SIGHUP] {
+            unsafe { libc::signal(signal, handle_signal as extern "C" fn(libc::c_int) as libc::sighandler_t) };
+        }
+
+        Ok(Session)
+    }
+
+    // hands the terminal back to the shell until the editor is continued with `fg`
+    pub fn suspend(&self) -> Result<(), Box<dyn std::error::Error>> {
+        io::stdout().flush()?;
+        leave();
+
+        unsafe { libc::raise(libc::SIGTSTP) };
+
+        enter()?;
+        Ok(())
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let _ = io::stdout().flush();
+        leave();
+    }
+}
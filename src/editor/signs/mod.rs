@@ -0,0 +1,171 @@
+use crate::editor::{Editor, LINE_NUMBER_WIDTH};
+use crate::editor::render::Frame;
+use crate::editor::theme::{Style, Color};
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+
+#[derive(Clone, Debug)]
+pub struct Sign {
+    pub text: char,
+    pub group: String,
+    // the 256 color index used when the theme does not style the group
+    pub color: u8,
+    pub priority: usize,
+    // shown at the end of the line with virtualtext
+    pub message: Option<String>,
+}
+
+// the signs of every file by the producer that placed them, a producer has one sign per line
+#[derive(Debug)]
+pub struct Signs {
+    placed: HashMap<String, HashMap<String, BTreeMap<usize, Sign>>>,
+    current: String,
+}
+
+fn key(filename: &str) -> String {
+    fs::canonicalize(filename).map(|path| path.to_string_lossy().to_string()).unwrap_or_else(|_| filename.to_string())
+}
+
+impl Signs {
+    pub fn new() -> Signs {
+        Signs {
+            placed: HashMap::new(),
+            current: String::new(),
+        }
+    }
+
+    // the file whose signs are drawn
+    pub fn open(&mut self, filename: &str) {
+        self.current = key(filename);
+    }
+
+    // replaces the signs of the producer in the file, the most important one of a line is kept
+    pub fn place(&mut self, producer: &str, filename: &str, signs: Vec<(usize, Sign)>) {
+        let mut lines: BTreeMap<usize, Sign> = BTreeMap::new();
+
+        for (line, sign) in signs {
+            if lines.get(&line).map(|known| known.priority < sign.priority).unwrap_or(true) {
+                lines.insert(line, sign);
+            }
+        }
+
+        self.placed.entry(key(filename)).or_default().insert(producer.to_string(), lines);
+    }
+
+    pub fn clear(&mut self, producer: &str, filename: &str) {
+        if let Some(producers) = self.placed.get_mut(&key(filename)) {
+            producers.remove(producer);
+        }
+    }
+
+    // moves the signs of the drawn file below `at` down by `count` lines
+    pub fn insert_lines(&mut self, at: usize, count: usize) {
+        self.shift(|line| Some(if line >= at { line + count } else { line }));
+    }
+
+    // the lines `at..at + count` were removed, their signs go with them
+    pub fn remove_lines(&mut self, at: usize, count: usize) {
+        self.shift(|line| match line {
+            line if line >= at + count => Some(line - count),
+            line if line >= at => None,
+            line => Some(line),
+        });
+    }
+
+    fn shift(&mut self, moved: impl Fn(usize) -> Option<usize>) {
+        for lines in self.placed.get_mut(&self.current).into_iter().flat_map(|producers| producers.values_mut()) {
+            *lines = std::mem::take(lines).into_iter()
+                .filter_map(|(line, sign)| moved(line).map(|line| (line, sign)))
+                .collect();
+        }
+    }
+
+    fn current(&self) -> impl Iterator<Item = &BTreeMap<usize, Sign>> {
+        self.placed.get(&self.current).into_iter().flat_map(|producers| producers.values())
+    }
+
+    pub fn at(&self, line: usize) -> Option<&Sign> {
+        self.current()
+            .filter_map(|lines| lines.get(&line))
+            .max_by_key(|sign| sign.priority)
+    }
+
+    // the most important sign of the line that has something to say
+    pub fn message(&self, line: usize) -> Option<&Sign> {
+        self.current()
+            .filter_map(|lines| lines.get(&line))
+            .filter(|sign| sign.message.is_some())
+            .max_by_key(|sign| sign.priority)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.current().all(|lines| lines.is_empty())
+    }
+}
+
+impl Editor {
+    // the letters of the marks, `<` and `>` only keep the last selection for a range
+    pub fn mark_signs(&mut self) {
+        let signs = self.marks.iter()
+            .filter(|(mark, _)| mark.is_ascii_lowercase())
+            .map(|(mark, line)| (*line, Sign { text: *mark, group: String::from("mark"), color: 6, priority: 1, message: None }))
+            .collect();
+
+        self.signs.place("marks", &self.filename, signs);
+    }
+
+    fn sign_column(&self) -> bool {
+        match self.options.get_str("signcolumn").as_str() {
+            "yes" => true,
+            "auto" => !self.signs.is_empty(),
+            _ => false,
+        }
+    }
+
+    // the columns left of the text
+    pub fn gutter(&self) -> usize {
+        if self.sign_column() { LINE_NUMBER_WIDTH + 1 } else { LINE_NUMBER_WIDTH }
+    }
+
+    fn sign_style(&self, sign: &Sign, base: Style) -> Style {
+        let short = sign.group.rsplit('.').next().unwrap_or(&sign.group);
+
+        if self.theme.has(&sign.group) || self.theme.has(short) {
+            self.theme.style(&sign.group)
+        } else {
+            Style { fg: Some(Color::Indexed(sign.color)), ..base }
+        }
+    }
+
+    // returns the column the line numbers start at
+    pub fn render_sign(&self, frame: &mut Frame, y: usize, line: usize) -> usize {
+        if !self.sign_column() {
+            return 0;
+        }
+
+        let numbers = self.theme.style("line_numbers");
+
+        match self.signs.at(line) {
+            Some(sign) => frame.put(0, y, &sign.text.to_string(), &self.sign_style(sign, Style { bold: true, ..numbers })),
+            None => frame.put(0, y, " ", &numbers),
+        }
+    }
+
+    pub fn render_virtual_text(&self, frame: &mut Frame, x: usize, y: usize, line: usize) -> usize {
+        if !self.options.get_bool("virtualtext") {
+            return x;
+        }
+
+        let sign = match self.signs.message(line) {
+            Some(sign) => sign,
+            None => return x,
+        };
+
+        let message = sign.message.as_deref().unwrap_or("").lines().next().unwrap_or("");
+        let default = self.theme.style("default");
+
+        let x = frame.put(x, y, "  ", &default);
+        frame.put(x, y, &format!("{} {}", sign.text, message), &self.sign_style(sign, Style { italic: true, ..default }))
+    }
+}
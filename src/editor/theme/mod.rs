@@ -0,0 +1,363 @@
+use crate::editor::config_path;
+
+use serde_json::Value;
+
+use std::collections::HashMap;
+use std::fs;
+use std::env;
+
+const MAX_LINKS: usize = 16;
+
+const ANSI_NAMES: [&str; 16] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    "bright_black", "bright_red", "bright_green", "bright_yellow", "bright_blue", "bright_magenta", "bright_cyan", "bright_white",
+];
+
+// xterm default values of the 16 ansi colors, used to find the nearest one
+const ANSI_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0), (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+    (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0), (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+];
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Depth {
+    Ansi,
+    Indexed,
+    TrueColor,
+}
+
+impl Depth {
+    pub fn detect(setting: &str) -> Depth {
+        match setting {
+            "16" => Depth::Ansi,
+            "256" => Depth::Indexed,
+            "truecolor" => Depth::TrueColor,
+            _ => {
+                let colorterm = env::var("COLORTERM").unwrap_or_default();
+                let term = env::var("TERM").unwrap_or_default();
+
+                if colorterm == "truecolor" || colorterm == "24bit" {
+                    Depth::TrueColor
+                } else if term.contains("256color") || !colorterm.is_empty() {
+                    Depth::Indexed
+                } else {
+                    Depth::Ansi
+                }
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let difference = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    difference(a.0, b.0) + difference(a.1, b.1) + difference(a.2, b.2)
+}
+
+impl Color {
+    fn parse(value: &Value) -> Result<Color, Box<dyn std::error::Error>> {
+        if let Some(index) = value.as_u64() {
+            return u8::try_from(index).map(Color::Indexed).map_err(|_| format!("color index out of range: {}", index).into());
+        }
+
+        let name = value.as_str().ok_or_else(|| format!("invalid color: {}", value))?;
+
+        if let Some(hex) = name.strip_prefix('#') {
+            if hex.len() != 6 || !hex.chars().all(|character| character.is_ascii_hexdigit()) {
+                return Err(format!("invalid color: `{}`", name).into());
+            }
+
+            let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16);
+            return Ok(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+        }
+
+        let name = name.to_lowercase().replace(['-', ' '], "_").replace("grey", "gray");
+        let name = if name == "gray" { "bright_black" } else { &name };
+
+        ANSI_NAMES.iter()
+            .position(|ansi| *ansi == name || ansi.replace('_', "") == name)
+            .map(|index| Color::Indexed(index as u8))
+            .ok_or_else(|| format!("unknown color: `{}`", name).into())
+    }
+
+    fn rgb(&self) -> (u8, u8, u8) {
+        match *self {
+            Color::Rgb(red, green, blue) => (red, green, blue),
+            Color::Indexed(index) if index < 16 => ANSI_RGB[index as usize],
+            Color::Indexed(index) if index < 232 => {
+                let index = index - 16;
+                (CUBE_LEVELS[(index / 36) as usize], CUBE_LEVELS[(index / 6 % 6) as usize], CUBE_LEVELS[(index % 6) as usize])
+            },
+            Color::Indexed(index) => {
+                let level = 8 + (index - 232) * 10;
+                (level, level, level)
+            },
+        }
+    }
+
+    fn nearest_indexed(&self) -> u8 {
+        if let Color::Indexed(index) = *self {
+            return index;
+        }
+
+        let rgb = self.rgb();
+        let cube = |channel: u8| CUBE_LEVELS.iter().enumerate().min_by_key(|(_, level)| (**level as i32 - channel as i32).abs()).map(|(index, _)| index as u8).unwrap_or(0);
+
+        let cube_index = 16 + 36 * cube(rgb.0) + 6 * cube(rgb.1) + cube(rgb.2);
+        let gray_index = 232 + ((rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3).saturating_sub(3).min(230) as u8 / 10;
+
+        if distance(Color::Indexed(gray_index).rgb(), rgb) < distance(Color::Indexed(cube_index).rgb(), rgb) {
+            gray_index
+        } else {
+            cube_index
+        }
+    }
+
+    fn nearest_ansi(&self) -> u8 {
+        if let Color::Indexed(index) = *self {
+            if index < 16 {
+                return index;
+            }
+        }
+
+        let rgb = self.rgb();
+        (0..16).min_by_key(|index| distance(ANSI_RGB[*index as usize], rgb)).unwrap_or(0)
+    }
+
+    // the sgr parameters for this color, `base` is 30 for foreground and 40 for background
+    fn sgr(&self, base: u8, depth: Depth) -> String {
+        match (depth, *self) {
+            (Depth::TrueColor, Color::Rgb(red, green, blue)) => format!("{};2;{};{};{}", base + 8, red, green, blue),
+            (Depth::TrueColor, Color::Indexed(index)) | (Depth::Indexed, Color::Indexed(index)) => format!("{};5;{}", base + 8, index),
+            (Depth::Indexed, color) => format!("{};5;{}", base + 8, color.nearest_indexed()),
+            (Depth::Ansi, color) => {
+                let index = color.nearest_ansi();
+                if index < 8 { format!("{}", base + index) } else { format!("{}", base + 60 + index - 8) }
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub undercurl: bool,
+}
+
+impl Style {
+    pub fn new() -> Style {
+        Style::default()
+    }
+
+    fn parse(value: &Value) -> Result<Style, Box<dyn std::error::Error>> {
+        // a group that is only a color sets the foreground
+        if !value.is_object() {
+            return Ok(Style {
+                fg: Some(Color::parse(value)?),
+                ..Style::default()
+            });
+        }
+
+        let color = |name: &str| -> Result<Option<Color>, Box<dyn std::error::Error>> {
+            if value[name].is_null() { Ok(None) } else { Color::parse(&value[name]).map(Some) }
+        };
+
+        Ok(Style {
+            fg: color("fg")?,
+            bg: color("bg")?,
+
+            bold: value["bold"].as_bool().unwrap_or(false),
+            italic: value["italic"].as_bool().unwrap_or(false),
+            underline: value["underline"].as_bool().unwrap_or(false),
+            undercurl: value["undercurl"].as_bool().unwrap_or(false),
+        })
+    }
+
+    pub fn sgr(&self, depth: Depth) -> String {
+        let mut parameters = vec![String::from("0")];
+
+        if self.bold {
+            parameters.push(String::from("1"));
+        }
+        if self.italic {
+            parameters.push(String::from("3"));
+        }
+        if self.undercurl && depth != Depth::Ansi {
+            parameters.push(String::from("4:3"));
+        } else if self.underline || self.undercurl {
+            parameters.push(String::from("4"));
+        }
+        if let Some(fg) = self.fg {
+            parameters.push(fg.sgr(30, depth));
+        }
+        if let Some(bg) = self.bg {
+            parameters.push(bg.sgr(40, depth));
+        }
+
+        format!("\x1b[{}m", parameters.join(";"))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub name: String,
+
+    groups: HashMap<String, Style>,
+    links: HashMap<String, String>,
+    background: Option<Color>,
+}
+
+impl Theme {
+    pub fn load(name: Option<&str>) -> Result<Theme, Box<dyn std::error::Error>> {
+        let name = match name {
+            Some(name) => name,
+            None => return Self::legacy(),
+        };
+
+        let path = config_path(&format!("themes/{}.json", name))?;
+        let content = fs::read_to_string(&path).map_err(|_| format!("Cannot find color scheme `{}`", name))?;
+        let json = serde_json::from_str::<Value>(&content).map_err(|err| format!("{}: {}", path, err))?;
+
+        let mut theme = Theme {
+            name: name.to_string(),
+
+            groups: HashMap::new(),
+            links: HashMap::new(),
+            background: None,
+        };
+
+        for (group, value) in json.as_object().ok_or_else(|| format!("{}: expected an object", path))? {
+            if group == "background" {
+                theme.background = Some(Color::parse(value).map_err(|err| format!("{}: {}", path, err))?);
+            } else if let Some(link) = value["link"].as_str() {
+                theme.links.insert(group.clone(), link.to_string());
+            } else {
+                theme.groups.insert(group.clone(), Style::parse(value).map_err(|err| format!("{}: `{}`: {}", path, group, err))?);
+            }
+        }
+
+        Ok(theme)
+    }
+
+    // colors.json only has 256 color indices and a fixed set of groups
+    fn legacy() -> Result<Theme, Box<dyn std::error::Error>> {
+        let mut theme = Theme {
+            name: String::from("default"),
+
+            groups: HashMap::new(),
+            links: HashMap::new(),
+            background: None,
+        };
+
+        let colors = match fs::read_to_string(config_path("colors.json")?) {
+            Ok(colors) => colors,
+            Err(_) => return Ok(theme),
+        };
+        let colors_json = serde_json::from_str::<Value>(&colors)?;
+
+        let index = |name: &str| Color::Indexed(colors_json[name].as_u64().unwrap_or(0) as u8);
+        let fg = |name: &str| Style { fg: Some(index(name)), ..Style::default() };
+
+        theme.background = Some(index("bg"));
+
+        // builtin
+        theme.groups.insert(String::from("keywords"), Style { bold: true, ..fg("keywords") });
+        theme.groups.insert(String::from("types"), fg("types"));
+        theme.groups.insert(String::from("operators"), fg("operators"));
+
+        // literals
+        theme.groups.insert(String::from("integers"), fg("integers"));
+        theme.groups.insert(String::from("strings"), fg("strings"));
+
+        // line numbers
+        theme.groups.insert(String::from("line_numbers"), fg("line_numbers"));
+
+        // bar color
+        theme.groups.insert(String::from("bar"), Style { bg: Some(index("bar")), ..Style::default() });
+
+        // mode colors
+        theme.groups.insert(String::from("mode"), Style { fg: Some(index("mode_fg")), bg: Some(index("mode_bg")), bold: true, ..Style::default() });
+
+        Ok(theme)
+    }
+
+    pub fn has(&self, group: &str) -> bool {
+        self.groups.contains_key(group) || self.links.contains_key(group)
+    }
+
+    pub fn style(&self, group: &str) -> Style {
+        self.resolve(group, None)
+    }
+
+    // `rust.lifetime` is styled by the theme's `rust.lifetime` or `lifetime`, otherwise the links of
+    // the theme and then the ones of the language are followed, groups nobody knows use `default`
+    pub fn resolve(&self, group: &str, links: Option<&HashMap<String, String>>) -> Style {
+        let mut group = group.to_string();
+        let mut style = None;
+
+        for _ in 0..MAX_LINKS {
+            let names = group.match_indices('.')
+                .map(|(index, _)| &group[index + 1..])
+                .collect::<Vec<&str>>();
+            let names = [&group[..]].into_iter().chain(names).collect::<Vec<&str>>();
+
+            if let Some(found) = names.iter().find_map(|name| self.groups.get(*name)) {
+                style = Some(found.clone());
+                break;
+            }
+
+            let link = names.iter()
+                .find_map(|name| self.links.get(*name))
+                .or_else(|| names.iter().find_map(|name| links.and_then(|links| links.get(*name))));
+
+            match link {
+                Some(link) => group = link.clone(),
+                None => break,
+            }
+        }
+
+        let mut style = style
+            .or_else(|| self.groups.get("default").cloned())
+            .unwrap_or_default();
+
+        if style.bg.is_none() {
+            style.bg = self.background;
+        }
+
+        style
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    #[test]
+    fn colors() {
+        assert_eq!(Color::parse(&json!("#ff8000")).unwrap(), Color::Rgb(255, 128, 0));
+        assert_eq!(Color::parse(&json!(42)).unwrap(), Color::Indexed(42));
+        assert_eq!(Color::parse(&json!("Bright-Red")).unwrap(), Color::Indexed(9));
+    }
+
+    #[test]
+    fn invalid_colors() {
+        // six bytes but three characters, it can not be cut into channels
+        for value in [json!("#ééé"), json!("#12345"), json!("#12345g"), json!(256), json!("purple")] {
+            assert!(Color::parse(&value).is_err(), "{}", value);
+        }
+    }
+}
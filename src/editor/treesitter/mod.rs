@@ -0,0 +1,290 @@
+use streaming_iterator::StreamingIterator;
+use tree_sitter::{Parser, Language, Query, QueryCursor, InputEdit, Point};
+
+use crate::editor::Change;
+
+use std::rc::Rc;
+use std::fmt;
+
+// grammars compiled into the editor, the extension decides which one is used
+fn grammar(extension: &str) -> Option<(&'static str, Language, &'static str)> {
+    Some(match extension {
+        "rs" => ("rust", tree_sitter_rust::LANGUAGE.into(), tree_sitter_rust::HIGHLIGHTS_QUERY),
+        "c" | "h" => ("c", tree_sitter_c::LANGUAGE.into(), tree_sitter_c::HIGHLIGHT_QUERY),
+        "py" => ("python", tree_sitter_python::LANGUAGE.into(), tree_sitter_python::HIGHLIGHTS_QUERY),
+        "json" => ("json", tree_sitter_json::LANGUAGE.into(), tree_sitter_json::HIGHLIGHTS_QUERY),
+        "toml" => ("toml", tree_sitter_toml_ng::LANGUAGE.into(), tree_sitter_toml_ng::HIGHLIGHTS_QUERY),
+        "md" => ("markdown", tree_sitter_md::LANGUAGE.into(), tree_sitter_md::HIGHLIGHT_QUERY_BLOCK),
+        _ => return None,
+    })
+}
+
+pub struct Node {
+    pub kind: String,
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+pub struct Tree {
+    pub filetype: &'static str,
+
+    language: Language,
+    parser: Parser,
+    query: Rc<Query>,
+    tree: Option<tree_sitter::Tree>,
+
+    source: String,
+    lines: Vec<usize>,
+}
+
+impl Tree {
+    pub fn new(extension: &str) -> Option<Tree> {
+        let (filetype, language, highlights) = grammar(extension)?;
+
+        let mut parser = Parser::new();
+        parser.set_language(&language).ok()?;
+
+        Some(Tree {
+            filetype,
+
+            query: Rc::new(Query::new(&language, highlights).ok()?),
+            language,
+            parser,
+            tree: None,
+
+            source: String::new(),
+            lines: vec![0],
+        })
+    }
+
+    pub fn capture_names(&self) -> &[&str] {
+        self.query.capture_names()
+    }
+
+    // every line ends in a newline, so a change covers whole lines and only they are parsed again
+    pub fn update(&mut self, changes: &[Change]) {
+        if changes.is_empty() {
+            return;
+        }
+
+        for change in changes {
+            let count = self.lines.len() - 1;
+            let start = change.start.min(count);
+            let end = start + change.removed.min(count - start);
+
+            let text = change.lines.iter().map(|line| line.clone() + "\n").collect::<String>();
+            let (start_byte, old_end_byte) = (self.lines[start], self.lines[end]);
+            let new_end_byte = start_byte + text.len();
+
+            if let Some(tree) = &mut self.tree {
+                tree.edit(&InputEdit {
+                    start_byte,
+                    old_end_byte,
+                    new_end_byte,
+                    start_position: Point::new(start, 0),
+                    old_end_position: Point::new(end, 0),
+                    new_end_position: Point::new(start + change.lines.len(), 0),
+                });
+            }
+
+            self.source.replace_range(start_byte..old_end_byte, &text);
+
+            // the lines after the change move by the difference in length
+            let tail = self.lines.split_off(end);
+            self.lines.truncate(start);
+
+            let mut offset = start_byte;
+            for line in &change.lines {
+                self.lines.push(offset);
+                offset += line.len() + 1;
+            }
+
+            self.lines.extend(tail.into_iter().map(|old| old - old_end_byte + new_end_byte));
+        }
+
+        self.tree = self.parser.parse(&self.source, self.tree.as_ref());
+    }
+
+    // every byte gets the capture of the smallest node around it, on a tie the earlier pattern wins
+    pub fn highlight(&self, row: usize, line: &str) -> Option<Vec<(&str, String)>> {
+        let tree = self.tree.as_ref()?;
+        let start = *self.lines.get(row)?;
+
+        if self.source.get(start..start + line.len())? != line {
+            return None;
+        }
+
+        let mut captures: Vec<Option<(usize, usize)>> = vec![None; line.len()];
+        let mut cursor = QueryCursor::new();
+        cursor.set_byte_range(start..start + line.len());
+
+        let mut matches = cursor.captures(&self.query, tree.root_node(), self.source.as_bytes());
+
+        while let Some((found, index)) = matches.next() {
+            let capture = found.captures[*index];
+            let range = capture.node.byte_range();
+            let length = range.len();
+
+            for byte in range.start.max(start)..range.end.min(start + line.len()) {
+                let current = &mut captures[byte - start];
+
+                if current.map(|(_, current)| length < current).unwrap_or(true) {
+                    *current = Some((capture.index as usize, length));
+                }
+            }
+        }
+
+        let names = self.query.capture_names();
+        let mut output: Vec<(&str, String)> = Vec::new();
+        let mut position = 0;
+
+        while position < line.len() {
+            let capture = captures[position].map(|(index, _)| index);
+            let mut end = position + 1;
+
+            while end < line.len() && (!line.is_char_boundary(end) || captures[end].map(|(index, _)| index) == capture) {
+                end += 1;
+            }
+
+            output.push((capture.map(|index| names[index]).unwrap_or("default"), line[position..end].to_string()));
+            position = end;
+        }
+
+        Some(output)
+    }
+
+    // every named node that spans lines, nodes that start on the same line share the outermost fold
+    pub fn fold_ranges(&self) -> Vec<(usize, usize)> {
+        let mut ranges: Vec<(usize, usize)> = Vec::new();
+        let tree = match &self.tree {
+            Some(tree) => tree,
+            None => return ranges,
+        };
+
+        let mut cursor = tree.walk();
+        let mut visited = false;
+
+        loop {
+            if !visited {
+                let node = cursor.node();
+                let (start, end) = (node.start_position().row, node.end_position().row);
+
+                if node.is_named() && start < end && node.parent().is_some() && !ranges.iter().any(|(known, _)| *known == start) {
+                    ranges.push((start, end));
+                }
+            }
+
+            if !visited && cursor.goto_first_child() {
+                continue;
+            }
+
+            if cursor.goto_next_sibling() {
+                visited = false;
+            } else if cursor.goto_parent() {
+                visited = true;
+            } else {
+                break;
+            }
+        }
+
+        ranges
+    }
+
+    // the cursor counts characters, tree-sitter counts bytes
+    fn point(&self, row: usize, column: usize) -> Option<Point> {
+        let start = *self.lines.get(row)?;
+        let line = self.source[start..].split('\n').next().unwrap_or("");
+        let column = line.char_indices().nth(column).map(|(index, _)| index).unwrap_or(line.len());
+
+        Some(Point::new(row, column))
+    }
+
+    fn position(&self, point: Point) -> (usize, usize) {
+        let start = self.lines[point.row];
+        (self.source[start..start + point.column].chars().count(), point.row)
+    }
+
+    // the largest named node that starts where the smallest one under the cursor does
+    fn outermost(&self, row: usize, column: usize) -> Option<tree_sitter::Node<'_>> {
+        let point = self.point(row, column)?;
+        let mut node = self.tree.as_ref()?.root_node().named_descendant_for_point_range(point, point)?;
+
+        while let Some(parent) = node.parent() {
+            if parent.parent().is_none() || parent.start_byte() != node.start_byte() {
+                break;
+            }
+
+            node = parent;
+        }
+
+        Some(node)
+    }
+
+    pub fn node_at(&self, row: usize, column: usize) -> Option<Node> {
+        let position = self.point(row, column)?;
+        let node = self.tree.as_ref()?.root_node().named_descendant_for_point_range(position, position)?;
+
+        Some(Node {
+            kind: node.kind().to_string(),
+            start: self.position(node.start_position()),
+            end: self.position(node.end_position()),
+        })
+    }
+
+    // the start of the next or previous node on the same level, a level further out when it has none
+    pub fn sibling(&self, row: usize, column: usize, forward: bool) -> Option<(usize, usize)> {
+        let mut node = self.outermost(row, column)?;
+
+        // going back from the middle of a node goes to its start first
+        if !forward && node.start_position() != self.point(row, column)? {
+            return Some(self.position(node.start_position()));
+        }
+
+        loop {
+            let sibling = if forward { node.next_named_sibling() } else { node.prev_named_sibling() };
+
+            if let Some(sibling) = sibling {
+                return Some(self.position(sibling.start_position()));
+            }
+
+            node = node.parent()?;
+        }
+    }
+
+    // the start of the node around the one under the cursor, the whole file does not count
+    pub fn parent(&self, row: usize, column: usize) -> Option<(usize, usize)> {
+        let node = self.outermost(row, column)?.parent()?;
+        node.parent()?;
+
+        Some(self.position(node.start_position()))
+    }
+}
+
+// parsers can not be shared, every buffer gets its own one
+impl Clone for Tree {
+    fn clone(&self) -> Tree {
+        let mut parser = Parser::new();
+        let _ = parser.set_language(&self.language);
+
+        Tree {
+            filetype: self.filetype,
+
+            language: self.language.clone(),
+            parser,
+            query: self.query.clone(),
+            tree: self.tree.clone(),
+
+            source: self.source.clone(),
+            lines: self.lines.clone(),
+        }
+    }
+}
+
+impl fmt::Debug for Tree {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_struct("Tree")
+            .field("filetype", &self.filetype)
+            .field("lines", &self.lines.len())
+            .finish()
+    }
+}
@@ -0,0 +1,69 @@
+use crate::editor::Editor;
+use crate::editor::signs::Sign;
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// "-start[,count] +start[,count]" of a hunk header, a missing count is 1
+fn hunk(header: &str) -> Option<(usize, usize, usize)> {
+    let mut ranges = header.strip_prefix("@@ ")?.split(' ');
+
+    let parse = |range: &str| -> Option<(usize, usize)> {
+        let (start, count) = range.split_once(',').unwrap_or((range, "1"));
+        Some((start.parse().ok()?, count.parse().ok()?))
+    };
+
+    let (_, removed) = parse(ranges.next()?.strip_prefix('-')?)?;
+    let (start, added) = parse(ranges.next()?.strip_prefix('+')?)?;
+
+    Some((start, removed, added))
+}
+
+fn sign(text: char, group: &str, color: u8) -> Sign {
+    Sign {
+        text,
+        group: group.to_string(),
+        color,
+        priority: 1,
+        message: None,
+    }
+}
+
+impl Editor {
+    // the lines that changed since the last commit, from `git diff`
+    pub fn vcs_signs(&mut self) {
+        let path = Path::new(&self.filename);
+        let directory = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let name = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+
+        let output = Command::new("git")
+            .arg("-C").arg(directory)
+            .args(["diff", "--no-color", "--no-ext-diff", "-U0", "--"])
+            .arg(&name)
+            .stderr(Stdio::null())
+            .output();
+
+        let diff = match output {
+            Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout).to_string(),
+            _ => {
+                self.signs.clear("vcs", &self.filename);
+                return;
+            },
+        };
+
+        let mut signs = Vec::new();
+
+        for (start, removed, added) in diff.lines().filter_map(hunk) {
+            if added == 0 {
+                // removed lines are shown on the line before them
+                signs.push((start.saturating_sub(1), sign('_', "vcs.removed", 1)));
+            } else {
+                let (text, group, color) = if removed == 0 { ('+', "vcs.added", 2) } else { ('~', "vcs.changed", 4) };
+                signs.extend((start - 1..start - 1 + added).map(|line| (line, sign(text, group, color))));
+            }
+        }
+
+        self.signs.place("vcs", &self.filename, signs);
+        self.refresh = true;
+    }
+}
@@ -23,17 +23,17 @@ fn main() {
     let mut edit = match editor::Editor::new(&args.pos_arg[1]) {
         Ok(edit) => edit,
         Err(err) => {
-            println!("Failed to create new editor instance -> `{}`", err.to_string());
+            println!("Failed to create new editor instance -> `{}`", err);
             process::exit(1);
         },
     };
 
-    if let Err(_) = edit.open_file(&args.pos_arg[1]) {
+    if edit.open_file(&args.pos_arg[1]).is_err() {
         edit.log(&format!("Failed to open `{}`", args.pos_arg[1]));
     }
 
     if let Err(err) = edit.run() {
-        println!("Failed to run main loop: `{}`", err.to_string());
+        println!("Failed to run main loop: `{}`", err);
     }
 }
 
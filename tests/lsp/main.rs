@@ -0,0 +1,240 @@
+mod server;
+
+use serde_json::{json, Value};
+
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::fd::{FromRawFd, OwnedFd};
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::process::{self, Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const EDITOR: &str = env!("CARGO_BIN_EXE_te");
+const SERVE: &str = "--serve";
+
+// keys are sent one chunk at a time, an escape followed too quickly by a key is an alt chord
+const KEY_DELAY: Duration = Duration::from_millis(200);
+const WAIT: Duration = Duration::from_secs(5);
+
+fn read_message(reader: &mut impl BufRead) -> Value {
+    let mut length = 0;
+
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).unwrap();
+
+        match header.trim_end().strip_prefix("Content-Length:") {
+            Some(value) => length = value.trim().parse::<usize>().unwrap(),
+            None if header.trim_end().is_empty() => break,
+            None => {},
+        }
+    }
+
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+// a directory with the files of the test, a home with the server config and the log of the server
+fn workspace(name: &str, files: &[(&str, &str)], arguments: &[&str]) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("te-lsp-{}-{}", process::id(), name));
+    let _ = fs::remove_dir_all(&directory);
+
+    fs::create_dir_all(directory.join("home/.config/te")).unwrap();
+    fs::create_dir_all(directory.join("log")).unwrap();
+
+    let mut arguments = arguments.iter().map(|argument| argument.to_string()).collect::<Vec<String>>();
+    arguments.extend([String::from(SERVE), String::from("--log"), directory.join("log").to_string_lossy().to_string()]);
+
+    let config = json!({"txt": {"command": env::current_exe().unwrap(), "args": arguments}});
+    fs::write(directory.join("home/.config/te/servers.json"), config.to_string()).unwrap();
+
+    for (name, text) in files {
+        fs::write(directory.join(name), text).unwrap();
+    }
+
+    directory
+}
+
+// the editor on a pseudo terminal, what it draws is read and thrown away
+struct Editor {
+    child: Child,
+    input: File,
+}
+
+impl Editor {
+    fn open(directory: &Path, file: &str) -> Editor {
+        let (mut master, mut slave) = (0, 0);
+        let size = libc::winsize { ws_row: 24, ws_col: 80, ws_xpixel: 0, ws_ypixel: 0 };
+
+        assert_eq!(unsafe { libc::openpty(&mut master, &mut slave, std::ptr::null_mut(), std::ptr::null(), &size) }, 0);
+
+        let master = unsafe { OwnedFd::from_raw_fd(master) };
+        let slave = unsafe { OwnedFd::from_raw_fd(slave) };
+
+        let child = Command::new(EDITOR)
+            .arg(file)
+            .current_dir(directory)
+            .env("HOME", directory.join("home"))
+            .env("TERM", "xterm-256color")
+            .stdin(Stdio::from(slave.try_clone().unwrap()))
+            .stdout(Stdio::from(slave.try_clone().unwrap()))
+            .stderr(Stdio::from(slave))
+            .spawn()
+            .unwrap();
+
+        let mut output = File::from(master.try_clone().unwrap());
+        thread::spawn(move || {
+            let mut buffer = [0; 4096];
+            while matches!(output.read(&mut buffer), Ok(length) if length > 0) {}
+        });
+
+        thread::sleep(KEY_DELAY);
+
+        Editor {
+            child,
+            input: File::from(master),
+        }
+    }
+
+    fn keys(&mut self, keys: &[&str]) {
+        for key in keys {
+            self.input.write_all(key.as_bytes()).unwrap();
+            thread::sleep(KEY_DELAY);
+        }
+    }
+}
+
+impl Drop for Editor {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+// the server writes the text it has for a document after every change
+fn server_text(directory: &Path, name: &str, expected: &str) {
+    let path = directory.join("log").join(name);
+    let start = Instant::now();
+
+    while fs::read_to_string(&path).map(|text| text != expected).unwrap_or(true) {
+        if start.elapsed() > WAIT {
+            panic!("the server has {:?} for `{}`, expected {:?}", fs::read_to_string(&path).ok(), name, expected);
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    }
+}
+
+// the editor tests talk to the mock server, it has to answer like a language server
+fn mock_server_answers_like_a_language_server() {
+    let mut server = Command::new(env::current_exe().unwrap())
+        .arg(SERVE)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut stdin = server.stdin.take().unwrap();
+    let mut stdout = BufReader::new(server.stdout.take().unwrap());
+
+    let mut send = |message: Value| {
+        let body = message.to_string();
+        write!(stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+        stdin.flush().unwrap();
+    };
+
+    send(json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}}));
+    assert_eq!(read_message(&mut stdout)["result"]["capabilities"]["textDocumentSync"], 2);
+
+    send(json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {"textDocument": {"uri": "file:///a", "text": "a bad\n"}}}));
+    assert_eq!(read_message(&mut stdout)["params"]["diagnostics"][0]["range"]["start"], json!({"line": 0, "character": 2}));
+
+    send(json!({"jsonrpc": "2.0", "method": "textDocument/didChange", "params": {"textDocument": {"uri": "file:///a"}, "contentChanges": [
+        {"range": {"start": {"line": 0, "character": 0}, "end": {"line": 0, "character": 2}}, "text": "so "},
+    ]}}));
+    read_message(&mut stdout);
+
+    send(json!({"jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": {"textDocument": {"uri": "file:///a"}}}));
+    assert_eq!(read_message(&mut stdout)["result"]["contents"]["value"], "so bad\n");
+
+    send(json!({"jsonrpc": "2.0", "method": "exit"}));
+    assert!(server.wait().unwrap().success());
+}
+
+fn edits_are_sent_as_they_are_typed() {
+    let directory = workspace("typed", &[("a.txt", "one\ntwo\n")], &[]);
+    let mut editor = Editor::open(&directory, "a.txt");
+
+    server_text(&directory, "a.txt", "one\ntwo\n");
+
+    editor.keys(&["o", "x", "\x1b"]);
+    server_text(&directory, "a.txt", "one\nx\ntwo\n");
+
+    editor.keys(&[":1d\r"]);
+    server_text(&directory, "a.txt", "x\ntwo\n");
+}
+
+fn full_sync_sends_the_whole_buffer() {
+    let directory = workspace("full", &[("a.txt", "one\n")], &["--full"]);
+    let mut editor = Editor::open(&directory, "a.txt");
+
+    editor.keys(&["o", "two", "\x1b"]);
+    server_text(&directory, "a.txt", "one\ntwo\n");
+}
+
+fn rename_opens_other_files_unsaved() {
+    let directory = workspace("rename", &[("a.txt", "foo bar\n"), ("b.txt", "x foo\n")], &[]);
+    let mut editor = Editor::open(&directory, "a.txt");
+
+    server_text(&directory, "a.txt", "foo bar\n");
+
+    editor.keys(&[":rename qux\r"]);
+    server_text(&directory, "a.txt", "qux bar\n");
+    server_text(&directory, "b.txt", "x qux\n");
+
+    assert_eq!(fs::read_to_string(directory.join("a.txt")).unwrap(), "foo bar\n");
+    assert_eq!(fs::read_to_string(directory.join("b.txt")).unwrap(), "x foo\n");
+}
+
+fn main() {
+    if env::args().any(|argument| argument == SERVE) {
+        server::run();
+        return;
+    }
+
+    let tests: [(&str, fn()); 4] = [
+        ("mock_server_answers_like_a_language_server", mock_server_answers_like_a_language_server),
+        ("edits_are_sent_as_they_are_typed", edits_are_sent_as_they_are_typed),
+        ("full_sync_sends_the_whole_buffer", full_sync_sends_the_whole_buffer),
+        ("rename_opens_other_files_unsaved", rename_opens_other_files_unsaved),
+    ];
+
+    // like the test harness, arguments that are not flags filter the tests by name
+    let filters = env::args().skip(1).filter(|argument| !argument.starts_with('-')).collect::<Vec<String>>();
+    let tests = tests.into_iter()
+        .filter(|(name, _)| filters.is_empty() || filters.iter().any(|filter| name.contains(filter.as_str())))
+        .collect::<Vec<(&str, fn())>>();
+
+    println!("\nrunning {} tests", tests.len());
+
+    let mut failed = 0;
+    for (name, test) in tests.iter() {
+        let passed = panic::catch_unwind(test).is_ok();
+        println!("test {} ... {}", name, if passed { "ok" } else { "FAILED" });
+
+        if !passed {
+            failed += 1;
+        }
+    }
+
+    let result = if failed == 0 { "ok" } else { "FAILED" };
+    println!("\ntest result: {}. {} passed; {} failed\n", result, tests.len() - failed, failed);
+
+    if failed > 0 {
+        process::exit(101);
+    }
+}
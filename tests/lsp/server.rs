@@ -0,0 +1,179 @@
+// a language server for the tests, it keeps the documents it is sent and answers from them
+//
+// hover answers with the whole document, every "bad" word is an error, rename replaces the
+// word in every document and in the files of the working directory, `--full` asks for the
+// whole text on every change and `--log DIRECTORY` writes every document there after each change
+
+use serde_json::{json, Value};
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+
+fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    let mut length = None;
+
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+
+    let mut body = vec![0; length?];
+    reader.read_exact(&mut body).ok()?;
+
+    serde_json::from_slice(&body).ok()
+}
+
+fn send(message: Value) {
+    let body = message.to_string();
+    let mut stdout = io::stdout();
+
+    let _ = write!(stdout, "Content-Length: {}\r\n\r\n{}", body.len(), body);
+    let _ = stdout.flush();
+}
+
+// positions count utf-16 code units
+fn offset(text: &str, position: &Value) -> usize {
+    let (line, character) = (position["line"].as_u64().unwrap_or(0) as usize, position["character"].as_u64().unwrap_or(0) as usize);
+    let start = text.split_inclusive('\n').take(line).map(str::len).sum::<usize>();
+
+    let mut units = 0;
+    for (index, character_at) in text[start..].char_indices() {
+        if units >= character || character_at == '\n' {
+            return start + index;
+        }
+        units += character_at.len_utf16();
+    }
+
+    text.len()
+}
+
+// every match of `word` as a range on its line
+fn ranges(text: &str, word: &str) -> Vec<Value> {
+    let mut found = Vec::new();
+
+    for (line, content) in text.split('\n').enumerate() {
+        for (index, _) in content.match_indices(word) {
+            let character = content[..index].encode_utf16().count();
+            let end = character + word.encode_utf16().count();
+
+            found.push(json!({"start": {"line": line, "character": character}, "end": {"line": line, "character": end}}));
+        }
+    }
+
+    found
+}
+
+fn publish(uri: &str, text: &str) {
+    let diagnostics = ranges(text, "bad").into_iter()
+        .map(|range| json!({"range": range, "severity": 1, "message": "bad word"}))
+        .collect::<Vec<Value>>();
+
+    send(json!({"jsonrpc": "2.0", "method": "textDocument/publishDiagnostics", "params": {"uri": uri, "diagnostics": diagnostics}}));
+}
+
+fn word_at(text: &str, position: &Value) -> String {
+    let offset = offset(text, position);
+    let word = |character: &char| character.is_alphanumeric() || *character == '_';
+
+    let before = text[..offset].chars().rev().take_while(word).collect::<Vec<char>>();
+    let after = text[offset..].chars().take_while(word);
+
+    before.into_iter().rev().chain(after).collect()
+}
+
+fn rename(documents: &HashMap<String, String>, position: &Value, name: &str) -> Value {
+    let word = documents.get(position["uri"].as_str().unwrap_or("")).map(|text| word_at(text, &position["position"])).unwrap_or_default();
+    let mut changes = serde_json::Map::new();
+
+    if word.is_empty() {
+        return Value::Null;
+    }
+
+    let directory = env::current_dir().unwrap_or_default();
+    let files = fs::read_dir(&directory).into_iter().flatten().flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .filter_map(|path| Some((format!("file://{}", path.to_string_lossy()), fs::read_to_string(&path).ok()?)));
+
+    for (uri, text) in documents.iter().map(|(uri, text)| (uri.clone(), text.clone())).chain(files) {
+        let edits = ranges(&text, &word).into_iter().map(|range| json!({"range": range, "newText": name})).collect::<Vec<Value>>();
+
+        if !edits.is_empty() && !changes.contains_key(&uri) {
+            changes.insert(uri, Value::Array(edits));
+        }
+    }
+
+    json!({"changes": changes})
+}
+
+// the text the server has for the document, under the name of its file
+fn log(directory: &Option<String>, uri: &str, text: &str) {
+    if let Some(directory) = directory {
+        let name = uri.rsplit('/').next().unwrap_or(uri);
+        let _ = fs::write(format!("{}/{}", directory, name), text);
+    }
+}
+
+pub fn run() {
+    let arguments = env::args().collect::<Vec<String>>();
+    let full = arguments.iter().any(|argument| argument == "--full");
+    let directory = arguments.iter().position(|argument| argument == "--log").and_then(|index| arguments.get(index + 1)).cloned();
+
+    let mut reader = BufReader::new(io::stdin());
+    let mut documents: HashMap<String, String> = HashMap::new();
+
+    while let Some(message) = read_message(&mut reader) {
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or("").to_string();
+
+        let result = match message["method"].as_str().unwrap_or("") {
+            "initialize" => json!({"capabilities": {"textDocumentSync": if full { 1 } else { 2 }, "hoverProvider": true, "renameProvider": true}}),
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or("").to_string();
+                log(&directory, &uri, &text);
+                publish(&uri, &text);
+                documents.insert(uri, text);
+                continue;
+            },
+            "textDocument/didChange" => {
+                let text = documents.entry(uri.clone()).or_default();
+
+                for change in params["contentChanges"].as_array().unwrap_or(&Vec::new()) {
+                    let replacement = change["text"].as_str().unwrap_or("");
+
+                    if change["range"].is_object() {
+                        let (start, end) = (offset(text, &change["range"]["start"]), offset(text, &change["range"]["end"]));
+                        text.replace_range(start..end, replacement);
+                    } else {
+                        *text = replacement.to_string();
+                    }
+                }
+
+                log(&directory, &uri, text);
+                publish(&uri, text);
+                continue;
+            },
+            "textDocument/hover" => json!({"contents": {"kind": "plaintext", "value": documents.get(&uri).cloned().unwrap_or_default()}}),
+            "textDocument/rename" => rename(&documents, &json!({"uri": uri, "position": params["position"]}), params["newName"].as_str().unwrap_or("")),
+            "exit" => break,
+            _ => Value::Null,
+        };
+
+        if !message["id"].is_null() {
+            send(json!({"jsonrpc": "2.0", "id": message["id"], "result": result}));
+        }
+    }
+}